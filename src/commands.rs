@@ -1,10 +1,15 @@
+use std::io::{IsTerminal, Write};
+use std::process::Stdio;
+
 use clap::Subcommand;
 
 use crate::config;
 use crate::error;
 use crate::latex;
 use crate::note;
+use crate::recent;
 use crate::rust;
+use crate::trash;
 use crate::{get_editor, verify_filename, Cli};
 
 #[derive(Subcommand)]
@@ -32,9 +37,34 @@ pub enum Commands {
     },
     /// Note taking functions
     Note {
+        /// Notebook to use instead of the default one
+        #[clap(long, global = true, value_parser)]
+        notebook: Option<String>,
+
         #[clap(subcommand)]
         command: note::Commands,
     },
+    /// List recently opened files
+    Recent {
+        /// Number of files to list
+        #[clap(short = 'n', long, value_parser, default_value_t = 20)]
+        count: usize,
+
+        /// Only list files of this kind
+        #[clap(short, long, value_enum)]
+        kind: Option<recent::Kind>,
+    },
+    /// Reopen the most recently opened file
+    Last {
+        /// Only reopen a file of this kind
+        #[clap(short, long, value_enum)]
+        kind: Option<recent::Kind>,
+    },
+    /// Recover or permanently delete removed files
+    Trash {
+        #[clap(subcommand)]
+        command: trash::Commands,
+    },
 }
 
 pub fn run_command(command: &str, args: &Vec<&str>) {
@@ -52,18 +82,58 @@ pub fn run_command(command: &str, args: &Vec<&str>) {
 
 pub fn run_editor(filepath: &str) {
     match verify_filename(filepath) {
-        Some(name) => run_command(get_editor().as_str(), &vec![name]),
+        Some(name) => {
+            recent::record(name);
+            run_command(get_editor().as_str(), &vec![name]);
+        }
         None => error!("Editor could not find file '{filepath}'"),
     }
 }
 
+/// Print text through `$PAGER` (or `less`) when stdout is a terminal
+pub fn run_pager(contents: &str) {
+    if !std::io::stdout().is_terminal() {
+        print!("{contents}");
+        return;
+    }
+
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| "less".to_string());
+    let mut words = pager.split_whitespace();
+
+    let child = words.next().and_then(|program| {
+        std::process::Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .spawn()
+            .ok()
+    });
+
+    // Print directly when the pager can't be run
+    let Some(mut child) = child else {
+        print!("{contents}");
+        return;
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager may exit before reading everything
+        let _ = stdin.write_all(contents.as_bytes());
+    }
+
+    if let Err(e) = child.wait() {
+        error!("Could not run pager '{pager}': {e}");
+    }
+}
+
 pub fn match_command(cli: &Cli) {
     match &cli.command {
         Commands::Open { file_name } => run_editor(file_name.as_str()),
         Commands::Rust { command } => rust::parse_command(command),
         Commands::Config { command } => config::parse_command(command),
         Commands::Latex { command } => latex::parse_command(command),
-        Commands::Note { command } => note::parse_command(command),
+        Commands::Note { command, .. } => note::parse_command(command),
+        Commands::Recent { count, kind } => recent::recent(*count, *kind),
+        Commands::Last { kind } => recent::last(*kind),
+        Commands::Trash { command } => trash::parse_command(command),
     }
 }
 
@@ -1,4 +1,5 @@
 use serde_derive::Deserialize;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::prelude::*;
 
@@ -15,7 +16,88 @@ pub struct Toml {
     pub doc: String,
     pub editor: Option<String>,
     pub editor_backup: Option<String>,
-    pub note: Option<String>,
+    pub note: Option<Note>,
+    pub front_matter: Option<bool>,
+    pub templates: Option<String>,
+    /// Notes directories by name, used alongside the one set by `note`
+    pub notebooks: Option<BTreeMap<String, String>>,
+    /// Notebook to use when none is given, otherwise the one set by `note`
+    pub default_notebook: Option<String>,
+}
+
+/// Name of the notebook set by the `note` key
+pub const DEFAULT_NOTEBOOK: &str = "default";
+
+impl Toml {
+    /// Every notebook by name, including the one set by the `note` key
+    #[must_use]
+    pub fn notebooks(&self) -> BTreeMap<&str, &str> {
+        let mut notebooks = self
+            .notebooks
+            .iter()
+            .flatten()
+            .map(|(name, dir)| (name.as_str(), dir.as_str()))
+            .collect::<BTreeMap<&str, &str>>();
+
+        if let Some(dir) = self.note.as_ref().and_then(Note::dir) {
+            notebooks.entry(DEFAULT_NOTEBOOK).or_insert(dir);
+        }
+
+        notebooks
+    }
+
+    /// Name of the notebook used when none is given
+    #[must_use]
+    pub fn default_notebook(&self) -> Option<&str> {
+        if let Some(name) = &self.default_notebook {
+            return Some(name);
+        }
+
+        let notebooks = self.notebooks();
+
+        if notebooks.contains_key(DEFAULT_NOTEBOOK) {
+            Some(DEFAULT_NOTEBOOK)
+        } else if notebooks.len() == 1 {
+            notebooks.into_keys().next()
+        } else {
+            None
+        }
+    }
+}
+
+/// The `note` key, either the notes directory or a table of note settings
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Note {
+    Dir(String),
+    Settings(NoteSettings),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NoteSettings {
+    pub dir: Option<String>,
+    /// Commit every change to a note in a git repository in the notes directory
+    pub git: Option<bool>,
+    /// Number of snapshots to keep of each note, `0` disables snapshots
+    pub history_limit: Option<usize>,
+}
+
+impl Note {
+    #[must_use]
+    pub fn dir(&self) -> Option<&str> {
+        match self {
+            Self::Dir(dir) => Some(dir),
+            Self::Settings(settings) => settings.dir.as_deref(),
+        }
+    }
+
+    #[must_use]
+    pub const fn settings(&self) -> Option<&NoteSettings> {
+        match self {
+            Self::Dir(_) => None,
+            Self::Settings(settings) => Some(settings),
+        }
+    }
 }
 
 #[derive(Subcommand)]
@@ -1,9 +1,13 @@
+use std::fmt::Write;
 use std::fs;
 use std::path::Path;
 
+use sha2::{Digest, Sha256};
+
 use crate::error;
 
-pub fn create(path_str: &String) {
+/// Create every directory leading up to the file at `path_str`
+pub fn create_parent_dirs(path_str: &String) {
     let filename = match Path::new(path_str.as_str()).file_name() {
         Some(name) => match name.to_str() {
             Some(name_str) => name_str,
@@ -17,6 +21,10 @@ pub fn create(path_str: &String) {
     if let Some(e) = fs::create_dir_all(dir_path).err() {
         error!("Could not create directory '{dir_path}: {e}");
     }
+}
+
+pub fn create(path_str: &String) {
+    create_parent_dirs(path_str);
 
     if let Some(e) = fs::File::options()
         .read(true)
@@ -28,3 +36,14 @@ pub fn create(path_str: &String) {
         error!("Could not create file '{path_str}': {e}");
     }
 }
+
+/// Get the hex encoded SHA-256 hash of some contents
+#[must_use]
+pub fn content_hash(contents: &[u8]) -> String {
+    Sha256::digest(contents)
+        .iter()
+        .fold(String::new(), |mut hash, byte| {
+            let _ = write!(hash, "{byte:02x}");
+            hash
+        })
+}
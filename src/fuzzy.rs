@@ -0,0 +1,165 @@
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+
+use crate::error;
+
+/// Most suggestions to show when a name is ambiguous
+const MAX_SUGGESTIONS: usize = 10;
+
+/// How closely a candidate matches, from best to worst
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Tier {
+    Exact,
+    Prefix,
+    Substring,
+    Subsequence,
+}
+
+/// Score how well `query` matches `target`, lower is better
+fn score_against(query: &str, target: &str) -> Option<(Tier, usize)> {
+    if target == query {
+        Some((Tier::Exact, 0))
+    } else if target.starts_with(query) {
+        Some((Tier::Prefix, target.len() - query.len()))
+    } else if let Some(position) = target.find(query) {
+        Some((Tier::Substring, position))
+    } else {
+        // Count the characters skipped over when matching in order
+        let mut target_chars = target.chars();
+        let mut gaps = 0;
+
+        for query_char in query.chars() {
+            loop {
+                let target_char = target_chars.next()?;
+
+                if target_char == query_char {
+                    break;
+                }
+
+                gaps += 1;
+            }
+        }
+
+        Some((Tier::Subsequence, gaps))
+    }
+}
+
+/// Score a candidate by its full name, its name without an extension, and its file name
+fn score(query: &str, candidate: &str) -> Option<(Tier, usize)> {
+    let query = query.to_lowercase();
+    let candidate = candidate.to_lowercase();
+
+    let path = Path::new(&candidate);
+    let stem = match path.extension() {
+        Some(_) => candidate
+            .rsplit_once('.')
+            .map_or(candidate.as_str(), |(stem, _)| stem),
+        None => candidate.as_str(),
+    };
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(&candidate);
+    let file_stem = path
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .unwrap_or(file_name);
+
+    [candidate.as_str(), stem, file_name, file_stem]
+        .into_iter()
+        .filter_map(|target| score_against(&query, target))
+        .min()
+}
+
+/// Rank the candidates which match `query`, best first
+#[must_use]
+pub fn rank(query: &str, candidates: &[String]) -> Vec<String> {
+    let mut scored = candidates
+        .iter()
+        .filter_map(|candidate| score(query, candidate).map(|s| (s, candidate)))
+        .collect::<Vec<((Tier, usize), &String)>>();
+    scored.sort();
+
+    scored
+        .into_iter()
+        .map(|(_, candidate)| candidate.clone())
+        .collect()
+}
+
+/// Find the only candidate which matches best, if there is one
+fn unique_best(query: &str, candidates: &[String]) -> Option<String> {
+    let tiers = candidates
+        .iter()
+        .filter_map(|candidate| score(query, candidate).map(|(tier, _)| (tier, candidate)))
+        .collect::<Vec<(Tier, &String)>>();
+
+    let best_tier = tiers.iter().map(|(tier, _)| *tier).min()?;
+    let mut best = tiers.into_iter().filter(|(tier, _)| *tier == best_tier);
+
+    match (best.next(), best.next()) {
+        (Some((_, candidate)), None) => Some(candidate.clone()),
+        _ => None,
+    }
+}
+
+fn prompt_choice(suggestions: &[String]) -> String {
+    for (index, suggestion) in suggestions.iter().enumerate() {
+        println!("\t{}) {suggestion}", index + 1);
+    }
+
+    print!("Choose a number (leave blank to cancel): ");
+    let _ = std::io::stdout().flush();
+
+    let mut input = String::new();
+    if let Err(e) = std::io::stdin().read_line(&mut input) {
+        error!("Could not parse input: {e}");
+    }
+
+    let input = input.trim();
+    if input.is_empty() {
+        // User cancels the choice
+        std::process::exit(0x1001);
+    }
+
+    match input.parse::<usize>() {
+        Ok(choice) if (1..=suggestions.len()).contains(&choice) => suggestions[choice - 1].clone(),
+        _ => error!("Invalid choice '{input}'"),
+    }
+}
+
+/// Resolve a shortened `name` to one of `candidates`
+///
+/// A unique match is returned directly, otherwise the ranked suggestions are offered as a
+/// numbered choice when stdin is a terminal, or printed before exiting when it isn't
+#[must_use]
+pub fn choose(name: &str, candidates: &[String], kind: &str) -> String {
+    if let Some(candidate) = unique_best(name, candidates) {
+        // Printed to stderr so the output of commands can still be piped
+        if candidate != name {
+            eprintln!("Using {kind} '{candidate}'");
+        }
+
+        return candidate;
+    }
+
+    let suggestions = rank(name, candidates)
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .collect::<Vec<String>>();
+
+    if suggestions.is_empty() {
+        error!("No {kind} matching '{name}' found");
+    }
+
+    println!("'{name}' matches more than one {kind}, did you mean:");
+
+    if std::io::stdin().is_terminal() {
+        prompt_choice(&suggestions)
+    } else {
+        for suggestion in &suggestions {
+            println!("\t{suggestion}");
+        }
+
+        std::process::exit(0x1000);
+    }
+}
@@ -1,11 +1,11 @@
-use std::fs::remove_dir_all;
 use std::path::Path;
 
 use clap::Subcommand;
 
 use crate::commands::{run_command, run_editor};
 use crate::config;
-use crate::{confirm, error, verify_filename};
+use crate::trash;
+use crate::{confirm, error, fuzzy, get_matching_files, verify_filename};
 
 #[derive(Subcommand)]
 pub enum Commands {
@@ -71,12 +71,14 @@ fn latex_open(file_name: &String) {
 
             let full_filename = format!("{}/{}", config.doc, path_name);
 
-            match verify_filename(&full_filename) {
-                Some(name) => {
-                    run_editor(name);
-                    // run_latex_preview(name);
-                }
-                None => error!("Latex file not found: '{full_filename}'"),
+            if let Some(name) = verify_filename(&full_filename) {
+                run_editor(name);
+                // run_latex_preview(name);
+            } else {
+                let candidates = get_matching_files(&config.doc, r"^[^.]");
+                let chosen = fuzzy::choose(file_name, &candidates, "latex file");
+
+                run_editor(format!("{}/{chosen}", config.doc).as_str());
             }
         }
         Err(e) => error!("{e}"),
@@ -91,10 +93,11 @@ fn latex_remove(file_name: &String) {
             match verify_filename(&filename) {
                 Some(name) => {
                     if confirm!("remove {file_name}") {
-                        match remove_dir_all(name) {
-                            Ok(_) => println!("Successfully deleted {name}"),
-                            Err(e) => error!("Could not remove latex file '{name}': {e}"),
-                        }
+                        let id = trash::move_to_trash(name);
+
+                        println!(
+                            "Moved {name} to the trash (restore with 'ntsr trash restore {id}')"
+                        );
                     } else {
                         // User denies confirmation
                         std::process::exit(0x1001);
@@ -7,13 +7,16 @@ pub mod commands;
 pub mod config;
 // pub mod error;
 pub mod file;
+pub mod fuzzy;
 pub mod latex;
 pub mod note;
+pub mod recent;
 pub mod rust;
 pub mod temp_control;
+pub mod trash;
 
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use clap::Parser;
 use regex::Regex;
@@ -111,6 +114,46 @@ pub fn get_matching_files(directory: &String, regex_string: &str) -> Vec<String>
     filtered_files
 }
 
+/// Recursively get every non-hidden file below `directory`, relative to it
+#[must_use]
+pub fn get_all_files(directory: &str) -> Vec<String> {
+    let mut files: Vec<String> = Vec::new();
+    let mut dirs = vec![PathBuf::from(directory)];
+
+    while let Some(dir) = dirs.pop() {
+        let entries = fs::read_dir(&dir)
+            .unwrap_or_else(|e| error!("Error while finding files in '{}': {e}", dir.display()));
+
+        for entry in entries {
+            let path = entry
+                .unwrap_or_else(|e| error!("Could not display file: {e}"))
+                .path();
+
+            let is_hidden = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_none_or(|name| name.starts_with('.'));
+
+            if is_hidden {
+                continue;
+            }
+
+            if path.is_dir() {
+                dirs.push(path);
+            } else if let Some(relative) = path
+                .strip_prefix(directory)
+                .ok()
+                .and_then(|relative| relative.to_str())
+            {
+                files.push(relative.to_string());
+            }
+        }
+    }
+
+    files.sort();
+    files
+}
+
 #[must_use]
 pub fn get_home() -> String {
     match std::env::var("HOME") {
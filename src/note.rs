@@ -1,9 +1,35 @@
-use std::fs::remove_dir_all;
+pub mod agenda;
+pub mod assets;
+pub mod capture;
+pub mod crypt;
+pub mod daily;
+pub mod enex;
+pub mod export;
+pub mod git;
+pub mod graph;
+pub mod history;
+pub mod links;
+pub mod list;
+pub mod meta;
+pub mod notebook;
+pub mod review;
+pub mod search;
+pub mod show;
+pub mod template;
+pub mod todo;
+pub mod vault;
 
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+use chrono::NaiveDate;
 use clap::Subcommand;
 use std::path::Path;
 
-use crate::{commands::run_editor, config, confirm, error, file, get_files, verify_file_and_dir};
+use crate::{
+    commands::run_editor, config, confirm, error, file, fuzzy, get_all_files, get_files, trash,
+    verify_file_and_dir, verify_filename,
+};
 
 #[derive(Subcommand)]
 pub enum Commands {
@@ -11,6 +37,53 @@ pub enum Commands {
     New {
         #[clap(value_parser)]
         file_name: String,
+
+        /// Tag to add to the note's metadata (can be repeated)
+        #[clap(short, long = "tag", value_parser)]
+        tags: Vec<String>,
+
+        /// Template to fill the note with
+        #[clap(short = 'T', long, value_parser)]
+        template: Option<String>,
+
+        /// Extra template variable in the form KEY=VALUE (can be repeated)
+        #[clap(long = "var", value_parser = template::parse_var)]
+        vars: Vec<(String, String)>,
+
+        /// Encrypt the note with a passphrase
+        #[clap(short, long, value_parser)]
+        encrypt: bool,
+
+        /// Write this text to the note instead of opening the editor
+        #[clap(short, long, value_parser, conflicts_with = "encrypt")]
+        message: Option<String>,
+
+        /// Write text read from stdin to the note instead of opening the editor
+        #[clap(long, value_parser, conflicts_with_all = &["encrypt", "message"])]
+        stdin: bool,
+
+        /// Prefix the written text with the current time
+        #[clap(short = 's', long, value_parser)]
+        timestamp: bool,
+    },
+    /// Add text to the end of a note without opening the editor, creating it if needed
+    Append {
+        /// Note to append to, e.g. 'inbox'
+        #[clap(value_parser)]
+        file_name: String,
+
+        /// Text to append, read from stdin when none is given
+        #[clap(value_parser)]
+        text: Vec<String>,
+
+        /// Prefix the text with the current time
+        #[clap(short = 's', long, value_parser)]
+        timestamp: bool,
+    },
+    /// Encrypt an existing note with a passphrase
+    Encrypt {
+        #[clap(value_parser)]
+        file_name: String,
     },
     /// Open a note
     Open {
@@ -22,8 +95,268 @@ pub enum Commands {
         #[clap(value_parser)]
         file_name: String,
     },
-    /// List files in notes directory
-    List,
+    /// List files in notes directory as a tree
+    List {
+        /// Only list notes with this tag
+        #[clap(short, long, value_parser)]
+        tag: Option<String>,
+
+        /// How many levels of folders to descend into
+        #[clap(short, long, value_parser)]
+        depth: Option<usize>,
+
+        /// Show the size, word count, modified and created time of each note
+        #[clap(short, long, value_parser)]
+        long: bool,
+
+        /// List notes in this order instead of as a tree (times and sizes are largest first)
+        #[clap(short, long, value_enum)]
+        sort: Option<list::SortKey>,
+
+        /// Reverse the order of the list
+        #[clap(short, long, value_parser)]
+        reverse: bool,
+
+        /// Most notes to list
+        #[clap(short = 'n', long, value_parser)]
+        limit: Option<usize>,
+    },
+    /// Copy files into the notebook's assets folder and link to them from a note
+    Attach {
+        #[clap(value_parser)]
+        file_name: String,
+
+        /// Files to attach
+        #[clap(value_parser, required = true)]
+        files: Vec<String>,
+    },
+    /// Manage the files attached to notes
+    Assets {
+        #[clap(subcommand)]
+        command: assets::Commands,
+    },
+    /// List the notebooks and how many notes each has
+    Notebooks,
+    /// Create a folder in the notes directory
+    Mkdir {
+        #[clap(value_parser)]
+        dir_name: String,
+    },
+    /// Move a note or folder within the notes directory
+    Mv {
+        /// Note or folder to move
+        #[clap(value_parser)]
+        source: String,
+
+        /// New location (moved inside it if it is an existing folder)
+        #[clap(value_parser)]
+        destination: String,
+
+        /// Show what would be moved and which links would be rewritten without changing anything
+        #[clap(short = 'n', long)]
+        dry_run: bool,
+    },
+    /// List every tag used in the notes directory
+    Tags,
+    /// List the available note templates
+    Templates,
+    /// Open (or create) the journal entry for today
+    #[clap(args_conflicts_with_subcommands = true)]
+    Daily {
+        #[clap(subcommand)]
+        command: Option<daily::Commands>,
+
+        /// Open yesterday's entry
+        #[clap(short, long, conflicts_with = "offset")]
+        yesterday: bool,
+
+        /// Open the entry for a date in the form YYYY-MM-DD
+        #[clap(short, long, value_parser = daily::parse_date)]
+        date: Option<NaiveDate>,
+
+        /// Number of days away from today (or from --date) to open
+        #[clap(short, long, value_parser, allow_hyphen_values = true)]
+        offset: Option<i64>,
+    },
+    /// List the checklist items of every note
+    #[clap(args_conflicts_with_subcommands = true)]
+    Todo {
+        #[clap(subcommand)]
+        command: Option<todo::Commands>,
+
+        /// List completed items instead of open ones
+        #[clap(short, long, value_parser)]
+        done: bool,
+
+        /// Only look at notes within this folder
+        #[clap(short, long = "in", value_parser)]
+        folder: Option<String>,
+    },
+    /// Show what's due or scheduled with @due(YYYY-MM-DD) or @scheduled(YYYY-MM-DD) markers
+    Agenda {
+        /// Number of days ahead to show, from today
+        #[clap(short, long, value_parser)]
+        days: Option<usize>,
+
+        /// Print the agenda as an iCalendar file instead, with every open item
+        #[clap(long, value_parser)]
+        ics: bool,
+
+        /// File to write the iCalendar file to
+        #[clap(short, long, value_parser, requires = "ics")]
+        output: Option<String>,
+    },
+    /// Quiz yourself on the `Q:`/`A:` pairs and `term :: definition` lines in notes
+    Review {
+        /// Only review cards from notes within this folder
+        #[clap(long, value_parser)]
+        notebook_dir: Option<String>,
+
+        /// Maximum number of cards to review
+        #[clap(short, long, value_parser, default_value_t = 20)]
+        limit: usize,
+
+        /// Show how many cards are new and due instead of reviewing them
+        #[clap(long, value_parser)]
+        stats: bool,
+    },
+    /// List the wiki-links in a note
+    Links {
+        #[clap(value_parser)]
+        file_name: String,
+    },
+    /// List the notes which link to a note
+    Backlinks {
+        #[clap(value_parser)]
+        file_name: String,
+    },
+    /// Export notes to other formats
+    Export {
+        /// Note to export
+        #[clap(value_parser, required_unless_present = "all")]
+        file_name: Option<String>,
+
+        /// Format to export to
+        #[clap(short, long, value_enum, default_value = "html")]
+        format: export::Format,
+
+        /// File to write to (or a directory when exporting all notes), prints to stdout if not given
+        #[clap(short, long, value_parser)]
+        output: Option<String>,
+
+        /// Export every note (along with an index page for HTML)
+        #[clap(short, long, conflicts_with = "file-name", requires = "output")]
+        all: bool,
+    },
+    /// Copy the notes and attachments in a folder or export file into the notes directory
+    Import {
+        /// Folder or file to import, such as an Obsidian vault or an Evernote export
+        #[clap(value_parser)]
+        source: String,
+
+        /// Format of the notes being imported
+        #[clap(short, long, value_enum, default_value = "obsidian")]
+        format: vault::ImportFormat,
+    },
+    /// Print the links between notes as a graph, in Graphviz DOT or JSON format
+    Graph {
+        /// Output format
+        #[clap(short, long, value_enum, default_value = "dot")]
+        format: graph::Format,
+
+        /// Only include notes linked to this one, following links in either direction
+        #[clap(short, long, value_parser)]
+        root: Option<String>,
+
+        /// Most links away from the root note to include
+        #[clap(short, long, value_parser, requires = "root")]
+        depth: Option<usize>,
+    },
+    /// Show the git history of a note
+    Log {
+        #[clap(value_parser)]
+        file_name: String,
+    },
+    /// Show changes to a note since a git revision (or the latest change if none is given)
+    Diff {
+        #[clap(value_parser)]
+        file_name: String,
+
+        #[clap(value_parser)]
+        rev: Option<String>,
+    },
+    /// List the snapshots recorded of a note
+    History {
+        #[clap(value_parser)]
+        file_name: String,
+
+        /// Show the changes since this snapshot number
+        #[clap(short, long, value_parser)]
+        diff: Option<usize>,
+
+        /// Snapshot number to compare against instead of the current note
+        #[clap(short, long, value_parser, requires = "diff")]
+        to: Option<usize>,
+    },
+    /// Print a note, or one of its snapshots using NAME@NUMBER
+    #[clap(visible_alias = "cat")]
+    Show {
+        #[clap(value_parser)]
+        file_name: String,
+
+        /// Print the note as stored, including its metadata, without a pager
+        #[clap(short, long, value_parser)]
+        raw: bool,
+
+        /// Only print these lines, as START:END, START: or :END
+        #[clap(short, long, value_parser = show::parse_lines, conflicts_with = "section")]
+        lines: Option<show::LineRange>,
+
+        /// Only print the markdown section with this heading
+        #[clap(short, long, value_parser)]
+        section: Option<String>,
+    },
+    /// Restore a note to one of its snapshots
+    Revert {
+        #[clap(value_parser)]
+        file_name: String,
+
+        /// Snapshot number to restore
+        #[clap(value_parser)]
+        number: usize,
+    },
+    /// Search the contents of every note
+    Search {
+        /// Pattern to search for
+        #[clap(value_parser)]
+        pattern: String,
+
+        /// Treat the pattern as plain text instead of a regex
+        #[clap(short = 'F', long)]
+        fixed: bool,
+
+        /// Ignore case when matching
+        #[clap(short, long)]
+        ignore_case: bool,
+
+        /// Number of lines of context to show around each match
+        #[clap(short = 'C', long, value_parser, default_value_t = 0)]
+        context: usize,
+    },
+}
+
+/// Get the directory of the notebook given with `--notebook`, or of the default notebook
+fn notes_dir() -> String {
+    let config = config::get().unwrap_or_else(|e| error!("{e}"));
+
+    let name = notebook::selected()
+        .or_else(|| config.default_notebook().map(str::to_string))
+        .unwrap_or_else(|| error!("No notes directory set"));
+
+    config.notebooks().get(name.as_str()).map_or_else(
+        || error!("Notebook '{name}' not found, see 'ntsr note notebooks'"),
+        |dir| (*dir).to_string(),
+    )
 }
 
 fn check_extension(filename: &String) -> String {
@@ -35,112 +368,620 @@ fn check_extension(filename: &String) -> String {
     }
 }
 
-fn note_new(filename: &String) {
-    match config::get() {
-        Ok(config) => {
-            let dir_path = match config.note {
-                Some(path) => path,
-                None => error!("No notes directory set"),
-            };
+/// Open a note in the editor, refreshing its metadata if it was changed
+pub fn edit_note(path: &str) {
+    if crypt::is_encrypted(path) {
+        crypt::edit_encrypted(path);
+        return;
+    }
 
-            let checked_filename = check_extension(filename);
-            let full_path = format!("{dir_path}/{checked_filename}");
+    let before = fs::read_to_string(path).ok();
 
-            if let Ok(path) = verify_file_and_dir(checked_filename.as_str(), dir_path.as_str()) {
-                println!("Note '{path}' already exists...\nOpening note");
+    run_editor(path);
 
-                run_editor(&full_path);
-            } else {
-                file::create(&full_path);
+    let Ok(after) = fs::read_to_string(path) else {
+        return;
+    };
 
-                run_editor(full_path.as_str());
+    if before.as_ref() != Some(&after) {
+        let after = meta::touch_updated(&after).map_or(after, |touched| {
+            if let Err(e) = fs::write(path, &touched) {
+                error!("Could not update metadata of '{path}': {e}");
             }
+
+            touched
+        });
+
+        history::record(path, before.as_deref(), &after);
+    }
+
+    if git::enabled() {
+        git::commit_note(&notebook::dir_containing(path), path);
+    }
+}
+
+/// Change a note without the editor, refreshing its metadata and recording the change
+fn write_note(path: &str, before: Option<&str>, after: String) {
+    let after = meta::touch_updated(&after).unwrap_or(after);
+
+    if let Err(e) = fs::write(path, &after) {
+        error!("Could not write to note '{path}': {e}");
+    }
+
+    history::record(path, before, &after);
+
+    if git::enabled() {
+        git::commit_note(&notebook::dir_containing(path), path);
+    }
+}
+
+/// Contents of a new note, its template filled in and metadata added
+fn initial_contents(
+    full_path: &str,
+    tags: &[String],
+    template_path: Option<&str>,
+    vars: &[(String, String)],
+) -> String {
+    let title = Path::new(full_path)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(full_path);
+
+    let contents = template_path.map_or_else(String::new, |template_path| {
+        template::render(template_path, title, vars)
+    });
+
+    // Templates are allowed to provide their own metadata
+    let front_matter = config::get().map_or(true, |config| config.front_matter.unwrap_or(true));
+    if front_matter && meta::split(&contents).0.is_none() {
+        format!("{}{contents}", meta::FrontMatter::new(title, tags).render())
+    } else {
+        contents
+    }
+}
+
+/// Create a note filled with its template and metadata
+fn create_note(
+    full_path: &String,
+    tags: &[String],
+    template_path: Option<&str>,
+    vars: &[(String, String)],
+) {
+    let contents = initial_contents(full_path, tags, template_path, vars);
+
+    create_note_with(full_path, &contents);
+}
+
+/// Create a note with the contents it starts with
+fn create_note_with(full_path: &String, contents: &str) {
+    file::create(full_path);
+
+    if let Err(e) = fs::write(full_path, contents) {
+        error!("Could not write to note '{full_path}': {e}");
+    }
+}
+
+fn note_new(
+    filename: &String,
+    tags: &[String],
+    template_name: Option<&str>,
+    vars: &[(String, String)],
+    encrypt: bool,
+    entry: Option<&str>,
+) {
+    let dir_path = notes_dir();
+
+    let template_path = template_name
+        .map(|name| template::find(name).unwrap_or_else(|| error!("Template '{name}' not found")));
+
+    // Notes take the extension of their template when they don't have one
+    let checked_filename = match template_path
+        .as_ref()
+        .and_then(|path| Path::new(path).extension())
+        .and_then(|ext| ext.to_str())
+    {
+        Some(ext) if Path::new(filename).extension().is_none() => format!("{filename}.{ext}"),
+        _ => check_extension(filename),
+    };
+    let checked_filename = if encrypt {
+        format!("{checked_filename}.{}", crypt::EXTENSION)
+    } else {
+        checked_filename
+    };
+    let full_path = format!("{dir_path}/{checked_filename}");
+
+    if let Some(entry) = entry {
+        if verify_file_and_dir(checked_filename.as_str(), dir_path.as_str()).is_ok() {
+            error!("Note '{checked_filename}' already exists, use 'ntsr note append' to add to it");
         }
-        Err(e) => error!("{e}"),
+
+        create_note(&full_path, tags, template_path.as_deref(), vars);
+        capture::append_to(&full_path, entry);
+
+        println!("Created note '{checked_filename}'");
+    } else if let Ok(path) = verify_file_and_dir(checked_filename.as_str(), dir_path.as_str()) {
+        println!("Note '{path}' already exists...\nOpening note");
+
+        edit_note(&full_path);
+    } else if encrypt {
+        // The title comes from the name without the encrypted extension
+        let plain_path = full_path
+            .strip_suffix(&format!(".{}", crypt::EXTENSION))
+            .unwrap_or(&full_path);
+        let contents = initial_contents(plain_path, tags, template_path.as_deref(), vars);
+
+        file::create_parent_dirs(&full_path);
+        crypt::create_encrypted(&full_path, &contents);
+    } else {
+        create_note(&full_path, tags, template_path.as_deref(), vars);
+
+        edit_note(full_path.as_str());
+    }
+}
+
+/// Resolve a note name to its path relative to the notes directory
+///
+/// Names without an extension are checked as `.txt` notes first, then against any note in
+/// the same folder with a matching file stem
+fn resolve_note(dir_path: &str, name: &str) -> Option<String> {
+    let checked_filename = check_extension(&name.to_string());
+
+    if verify_file_and_dir(&checked_filename, dir_path).is_ok() {
+        return Some(checked_filename);
+    }
+
+    let encrypted_filename = format!("{checked_filename}.{}", crypt::EXTENSION);
+    if verify_file_and_dir(&encrypted_filename, dir_path).is_ok() {
+        return Some(encrypted_filename);
+    }
+
+    if Path::new(name).extension().is_some() {
+        return None;
+    }
+
+    let (folder, stem) = match name.rsplit_once('/') {
+        Some((folder, stem)) => (Some(folder), stem),
+        None => (None, name),
+    };
+    let folder_path = folder.map_or_else(|| dir_path.to_string(), |f| format!("{dir_path}/{f}"));
+
+    verify_filename(&folder_path)?;
+
+    let mut candidates = get_files(&folder_path)
+        .into_iter()
+        .filter(|file| {
+            let plain_name = file
+                .strip_suffix(&format!(".{}", crypt::EXTENSION))
+                .unwrap_or(file);
+
+            Path::new(plain_name).file_stem().and_then(|s| s.to_str()) == Some(stem)
+                && Path::new(&format!("{folder_path}/{file}")).is_file()
+        })
+        .collect::<Vec<String>>();
+    candidates.sort();
+
+    candidates
+        .into_iter()
+        .next()
+        .map(|file| folder.map_or_else(|| file.clone(), |f| format!("{f}/{file}")))
+}
+
+/// Get every note in a notes directory, leaving out attachments
+fn note_files(dir_path: &str) -> Vec<String> {
+    get_all_files(dir_path)
+        .into_iter()
+        .filter(|file_name| !assets::is_asset(file_name))
+        .collect()
+}
+
+/// Resolve a note name, falling back to fuzzy matching against every note
+fn find_note(dir_path: &str, name: &str) -> String {
+    resolve_note(dir_path, name)
+        .unwrap_or_else(|| fuzzy::choose(name, &note_files(dir_path), "note"))
+}
+
+/// Notes within a folder of the notes directory, or every note, relative to the notes directory
+fn notes_in(dir_path: &str, folder: Option<&str>) -> Vec<String> {
+    let Some(folder) = folder.map(|folder| folder.trim_matches('/')) else {
+        return note_files(dir_path);
+    };
+
+    let search_path = format!("{dir_path}/{folder}");
+
+    if verify_filename(&search_path).is_none() {
+        error!("Folder '{folder}' not found in '{dir_path}'");
     }
+
+    get_all_files(&search_path)
+        .into_iter()
+        .map(|file_name| format!("{folder}/{file_name}"))
+        .filter(|note| !assets::is_asset(note))
+        .collect()
 }
 
-fn note_open(filename: &String) {
-    match config::get() {
-        Ok(config) => {
-            let dir_path = match config.note {
-                Some(path) => path,
-                None => error!("No notes directory set"),
-            };
+/// Whether a line opens or closes a fenced code block
+fn is_code_fence(line: &str) -> bool {
+    line.trim_start().starts_with("```")
+}
+
+/// Resolve a note name without fuzzy matching, for commands which change the note
+fn find_note_exact(dir_path: &str, name: &str) -> String {
+    resolve_note(dir_path, name).unwrap_or_else(|| {
+        error!("Note '{name}' not found, notes are changed only by their exact name")
+    })
+}
+
+fn note_open(filename: &str) {
+    let dir_path = notes_dir();
+
+    edit_note(&format!("{dir_path}/{}", find_note(&dir_path, filename)));
+}
+
+fn note_remove(filename: &String) {
+    let dir_path = notes_dir();
+
+    match resolve_note(&dir_path, filename) {
+        Some(checked_filename) => {
+            let name = format!("{dir_path}/{checked_filename}");
 
-            let checked_filename = check_extension(filename);
+            if confirm!("remove {checked_filename}") {
+                let id = trash::move_to_trash(&name);
 
-            match verify_file_and_dir(checked_filename.as_str(), dir_path.as_str()) {
-                Ok(path) => run_editor(&path),
-                Err(e) => error!("Note error: {e}"),
+                println!("Moved {name} to the trash (restore with 'ntsr trash restore {id}')");
+            } else {
+                // User denies confirmation
+                std::process::exit(0x1001);
             }
         }
-        Err(e) => error!("{e}"),
+        None => error!("Note '{filename}' not found in '{dir_path}'"),
     }
 }
 
-fn note_remove(filename: &String) {
-    match config::get() {
-        Ok(config) => {
-            let dir_path = match config.note {
-                Some(path) => path,
-                None => error!("No notes directory set"),
-            };
-
-            let checked_filename = check_extension(filename);
-
-            match verify_file_and_dir(checked_filename.as_str(), dir_path.as_str()) {
-                Ok(name) => {
-                    if confirm!("remove {checked_filename}") {
-                        match remove_dir_all(checked_filename) {
-                            Ok(_) => println!("Successfully deleted {name}"),
-                            Err(e) => error!("Could not remove note '{name}': {e}"),
-                        }
-                    } else {
-                        // User denies confirmation
-                        std::process::exit(0x1001);
-                    }
-                }
-                Err(e) => error!("Note '{checked_filename}' not found: {e}"),
+fn print_tree(directory: &str, depth: usize, max_depth: Option<usize>) {
+    // Attachments aren't notes, so they are left out
+    let mut files = get_files(&directory.to_string())
+        .into_iter()
+        .filter(|file| !file.starts_with('.'))
+        .filter(|file| depth > 1 || file != assets::ASSETS_DIR)
+        .collect::<Vec<String>>();
+    files.sort();
+
+    let indent = "\t".repeat(depth);
+
+    for file in files {
+        let path = format!("{directory}/{file}");
+
+        if Path::new(&path).is_dir() {
+            println!("{indent}{file}/");
+
+            if max_depth.is_none_or(|max| depth < max) {
+                print_tree(&path, depth + 1, max_depth);
             }
+        } else if crypt::is_encrypted(&file) {
+            println!("{indent}{file} (encrypted)");
+        } else {
+            println!("{indent}{file}");
         }
-        Err(e) => error!("{e}"),
     }
 }
 
-fn note_list() {
-    match config::get() {
-        Ok(config) => {
-            let notes_dir = match config.note {
-                Some(path) => path,
-                None => error!("No notes directory set"),
-            };
+fn note_list(
+    tag: Option<&str>,
+    max_depth: Option<usize>,
+    long: bool,
+    sort: Option<list::SortKey>,
+    reverse: bool,
+    limit: Option<usize>,
+) {
+    // Trees are always in name order, so any other listing is flat
+    if tag.is_some() || long || sort.is_some() || reverse || limit.is_some() {
+        list::note_list_flat(
+            tag,
+            long,
+            sort.unwrap_or(list::SortKey::Name),
+            reverse,
+            limit,
+        );
+        return;
+    }
+
+    let notes_dir = notes_dir();
+
+    let shortened_notes_dir = match Path::new(&notes_dir).file_name() {
+        Some(dir) => match dir.to_str() {
+            Some(string) => string,
+            None => error!("Could not parse notes directory name into string"),
+        },
+        None => error!("Could not get name of notes directory"),
+    };
+
+    println!("{shortened_notes_dir}/");
+
+    print_tree(&notes_dir, 1, max_depth);
+}
 
-            let files = get_files(&notes_dir);
+fn note_mkdir(dir_name: &str) {
+    let full_path = format!("{}/{dir_name}", notes_dir());
 
-            let shortened_notes_dir = match Path::new(&notes_dir).file_name() {
-                Some(dir) => match dir.to_str() {
-                    Some(string) => string,
-                    None => error!("Could not parse notes directory name into string"),
-                },
-                None => error!("Could not get name of notes directory"),
-            };
+    if verify_filename(&full_path).is_some() {
+        error!("'{dir_name}' already exists");
+    }
+
+    match fs::create_dir_all(&full_path) {
+        Ok(()) => println!("Created folder '{dir_name}'"),
+        Err(e) => error!("Could not create folder '{full_path}': {e}"),
+    }
+}
+
+/// Resolve a note or folder name to its path relative to the notes directory
+fn resolve_entry(dir_path: &str, name: &str) -> String {
+    if verify_filename(&format!("{dir_path}/{name}")).is_some() {
+        name.trim_end_matches('/').to_string()
+    } else {
+        resolve_note(dir_path, name)
+            .unwrap_or_else(|| error!("Note error: File not found '{dir_path}/{name}'"))
+    }
+}
 
-            println!("{shortened_notes_dir}/");
+fn note_mv(source: &str, destination: &str, dry_run: bool) {
+    let dir_path = notes_dir();
 
-            for file in files {
-                println!("\t{file}");
+    let source_name = resolve_entry(&dir_path, source);
+    let source_path = format!("{dir_path}/{source_name}");
+    let source_is_dir = Path::new(&source_path).is_dir();
+
+    let destination = destination.trim_end_matches('/');
+    let destination_name = if Path::new(&format!("{dir_path}/{destination}")).is_dir() {
+        let file_name = Path::new(&source_name)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(&source_name);
+
+        format!("{destination}/{file_name}")
+    } else if source_is_dir {
+        destination.to_string()
+    } else {
+        // Keep the note's extension when none is given
+        match Path::new(&source_name)
+            .extension()
+            .and_then(|ext| ext.to_str())
+        {
+            Some(ext) if Path::new(destination).extension().is_none() => {
+                format!("{destination}.{ext}")
             }
+            _ => destination.to_string(),
+        }
+    };
+    let destination_path = format!("{dir_path}/{destination_name}");
+
+    if verify_filename(&destination_path).is_some() {
+        error!("'{destination_name}' already exists");
+    }
+
+    if source_is_dir && destination_path.starts_with(&format!("{source_path}/")) {
+        error!("Cannot move '{source_name}' inside itself");
+    }
+
+    let moves = if source_is_dir {
+        get_all_files(&source_path)
+            .into_iter()
+            .map(|file| {
+                (
+                    format!("{source_name}/{file}"),
+                    format!("{destination_name}/{file}"),
+                )
+            })
+            .collect::<BTreeMap<String, String>>()
+    } else {
+        BTreeMap::from([(source_name.clone(), destination_name.clone())])
+    };
+
+    let rewrites = links::plan_rewrites(&dir_path, &moves);
+
+    if dry_run {
+        println!("Would move '{source_name}' to '{destination_name}'");
+    } else {
+        file::create_parent_dirs(&destination_path);
+
+        match fs::rename(&source_path, &destination_path) {
+            Ok(()) => println!("Moved '{source_name}' to '{destination_name}'"),
+            Err(e) => error!("Could not move '{source_name}' to '{destination_name}': {e}"),
+        }
+
+        if git::enabled() {
+            let paths = moves
+                .iter()
+                .flat_map(|(old, new)| [format!("{dir_path}/{old}"), format!("{dir_path}/{new}")])
+                .collect::<Vec<String>>();
+
+            git::commit_files(
+                &notebook::dir_containing(&destination_path),
+                &paths,
+                &format!("move: {source_name} -> {destination_name}"),
+            );
         }
-        Err(e) => error!("{e}"),
     }
+
+    for rewrite in &rewrites {
+        let file_name = moves.get(&rewrite.file_name).unwrap_or(&rewrite.file_name);
+
+        for change in &rewrite.changes {
+            println!(
+                "\t{file_name}:{}: {} -> {}",
+                change.line, change.old, change.new
+            );
+        }
+
+        if !dry_run {
+            let path = format!("{dir_path}/{file_name}");
+            let before = fs::read_to_string(&path).ok();
+
+            write_note(&path, before.as_deref(), rewrite.contents.clone());
+        }
+    }
+
+    let link_count = rewrites.iter().map(|r| r.changes.len()).sum::<usize>();
+
+    println!(
+        "{} {link_count} links in {} notes",
+        if dry_run { "Would rewrite" } else { "Rewrote" },
+        rewrites.len()
+    );
 }
 
+/// Get the tags of every note, keyed by the note's path relative to the notes directory
+fn note_tags_by_file(dir_path: &str) -> Vec<(String, Vec<String>)> {
+    note_files(dir_path)
+        .into_iter()
+        .filter_map(|file_name| {
+            let contents = fs::read_to_string(format!("{dir_path}/{file_name}")).ok()?;
+
+            meta::parse(&contents).map(|front_matter| (file_name, front_matter.tags))
+        })
+        .collect()
+}
+
+fn note_tags() {
+    let dir_path = notes_dir();
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+
+    for (_, tags) in note_tags_by_file(&dir_path) {
+        for tag in tags {
+            *counts.entry(tag).or_default() += 1;
+        }
+    }
+
+    let mut counts = counts.into_iter().collect::<Vec<(String, usize)>>();
+    counts.sort_by(|(a_tag, a_count), (b_tag, b_count)| {
+        b_count.cmp(a_count).then_with(|| a_tag.cmp(b_tag))
+    });
+
+    for (tag, count) in counts {
+        println!("{count:>4}  {tag}");
+    }
+}
+
+#[allow(clippy::too_many_lines)]
 pub fn parse_command(command: &Commands) {
     match command {
-        Commands::New { file_name } => note_new(file_name),
+        Commands::New {
+            file_name,
+            tags,
+            template,
+            vars,
+            encrypt,
+            message,
+            stdin,
+            timestamp,
+        } => {
+            let entry = (message.is_some() || *stdin).then(|| {
+                capture::entry(&capture::read_text(message.as_slice(), *stdin), *timestamp)
+            });
+
+            note_new(
+                file_name,
+                tags,
+                template.as_deref(),
+                vars,
+                *encrypt,
+                entry.as_deref(),
+            );
+        }
+        Commands::Append {
+            file_name,
+            text,
+            timestamp,
+        } => capture::note_append(file_name, text, *timestamp),
+        Commands::Encrypt { file_name } => crypt::note_encrypt(file_name),
         Commands::Open { file_name } => note_open(file_name),
         Commands::Rm { file_name } => note_remove(file_name),
-        Commands::List => note_list(),
+        Commands::List {
+            tag,
+            depth,
+            long,
+            sort,
+            reverse,
+            limit,
+        } => note_list(tag.as_deref(), *depth, *long, *sort, *reverse, *limit),
+        Commands::Attach { file_name, files } => assets::note_attach(file_name, files),
+        Commands::Assets { command } => assets::parse_command(command),
+        Commands::Notebooks => notebook::note_notebooks(),
+        Commands::Mkdir { dir_name } => note_mkdir(dir_name),
+        Commands::Mv {
+            source,
+            destination,
+            dry_run,
+        } => note_mv(source, destination, *dry_run),
+        Commands::Tags => note_tags(),
+        Commands::Templates => template::note_templates(),
+        Commands::Daily {
+            command,
+            yesterday,
+            date,
+            offset,
+        } => match command {
+            Some(command) => daily::parse_command(command),
+            None => daily::note_daily(daily::entry_date(*yesterday, *date, *offset)),
+        },
+        Commands::Todo {
+            command,
+            done,
+            folder,
+        } => match command {
+            Some(command) => todo::parse_command(command),
+            None => todo::note_todo(*done, folder.as_deref()),
+        },
+        Commands::Agenda { days, ics, output } => {
+            agenda::note_agenda(*days, *ics, output.as_deref());
+        }
+        Commands::Review {
+            notebook_dir,
+            limit,
+            stats,
+        } => {
+            if *stats {
+                review::review_stats(notebook_dir.as_deref());
+            } else {
+                review::note_review(notebook_dir.as_deref(), *limit);
+            }
+        }
+        Commands::Links { file_name } => links::note_links(file_name),
+        Commands::Backlinks { file_name } => links::note_backlinks(file_name),
+        Commands::Export {
+            file_name,
+            format,
+            output,
+            all,
+        } => export::note_export(file_name.as_deref(), *format, output.as_deref(), *all),
+        Commands::Import { source, format } => vault::note_import(source, *format),
+        Commands::Graph {
+            format,
+            root,
+            depth,
+        } => graph::note_graph(*format, root.as_deref(), *depth),
+        Commands::Log { file_name } => git::note_log(file_name),
+        Commands::Diff { file_name, rev } => git::note_diff(file_name, rev.as_deref()),
+        Commands::History {
+            file_name,
+            diff,
+            to,
+        } => match diff {
+            Some(from) => history::note_history_diff(file_name, *from, *to),
+            None => history::note_history(file_name),
+        },
+        Commands::Show {
+            file_name,
+            raw,
+            lines,
+            section,
+        } => show::note_show(file_name, *raw, *lines, section.as_deref()),
+        Commands::Revert { file_name, number } => history::note_revert(file_name, *number),
+        Commands::Search {
+            pattern,
+            fixed,
+            ignore_case,
+            context,
+        } => search::note_search(pattern, *fixed, *ignore_case, *context),
     };
 }
@@ -0,0 +1,456 @@
+use std::fmt::Write;
+use std::fs;
+use std::io::IsTerminal;
+
+use chrono::{Days, Duration, Local, NaiveDate, NaiveTime, Utc};
+use regex::Regex;
+
+use super::{crypt, is_code_fence, note_files, notes_dir, todo};
+use crate::{error, file};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const TIME_FORMAT: &str = "%H:%M";
+
+/// Lines of an iCalendar file are folded once they reach this many bytes
+const ICS_LINE_LIMIT: usize = 75;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Kind {
+    Due,
+    Scheduled,
+}
+
+impl Kind {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Due => "due",
+            Self::Scheduled => "scheduled",
+        }
+    }
+
+    const fn label(self) -> &'static str {
+        match self {
+            Self::Due => "Due",
+            Self::Scheduled => "Scheduled",
+        }
+    }
+}
+
+/// A dated line of a note
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct Entry {
+    date: NaiveDate,
+    /// `None` for items which last all day
+    time: Option<NaiveTime>,
+    kind: Kind,
+    note: String,
+    /// Line number, starting at 1
+    line: usize,
+    text: String,
+}
+
+/// Matches `@due(...)` and `@scheduled(...)` markers, capturing their kind and date
+fn marker_regex() -> Regex {
+    match Regex::new(r"@(due|scheduled)\(([^()]*)\)") {
+        Ok(re) => re,
+        Err(e) => error!("Error with marker regex: {e}"), // User should not receive this message
+    }
+}
+
+/// Matches the list bullet or heading at the start of a line
+fn prefix_regex() -> Regex {
+    match Regex::new(r"^\s*(?:[-*+]\s+|\d+[.)]\s+|#+\s+)?") {
+        Ok(re) => re,
+        Err(e) => error!("Error with prefix regex: {e}"), // User should not receive this message
+    }
+}
+
+/// Parse the date of a marker, in the form YYYY-MM-DD with an optional HH:MM
+fn parse_when(when: &str) -> Option<(NaiveDate, Option<NaiveTime>)> {
+    let when = when.trim();
+
+    let (date, time) = match when.split_once([' ', 'T']) {
+        Some((date, time)) => (date, Some(time.trim())),
+        None => (when, None),
+    };
+
+    let date = NaiveDate::parse_from_str(date, DATE_FORMAT).ok()?;
+
+    match time {
+        Some(time) => Some((
+            date,
+            Some(NaiveTime::parse_from_str(time, TIME_FORMAT).ok()?),
+        )),
+        None => Some((date, None)),
+    }
+}
+
+/// Everything dated in the notes, leaving out completed items
+///
+/// Also returns the markers whose dates couldn't be read, as `note:line` and the marker
+fn find_entries(dir_path: &str) -> (Vec<Entry>, Vec<(String, String)>) {
+    let markers = marker_regex();
+    let items = todo::item_regex();
+    let prefix = prefix_regex();
+
+    let mut entries = Vec::new();
+    let mut invalid = Vec::new();
+
+    for note in note_files(dir_path) {
+        if crypt::is_encrypted(&note) {
+            continue;
+        }
+
+        let Ok(contents) = fs::read_to_string(format!("{dir_path}/{note}")) else {
+            continue;
+        };
+
+        let mut in_code_block = false;
+
+        for (index, line) in contents.lines().enumerate() {
+            if is_code_fence(line) {
+                in_code_block = !in_code_block;
+            }
+
+            if in_code_block || !markers.is_match(line) {
+                continue;
+            }
+
+            let (done, text) = items.captures(line).map_or((false, line), |captures| {
+                (
+                    &captures[2] != " ",
+                    captures.get(4).map_or("", |m| m.as_str()),
+                )
+            });
+
+            if done {
+                continue;
+            }
+
+            let text = markers.replace_all(text, "");
+            let text = prefix
+                .replace(&text, "")
+                .split_whitespace()
+                .collect::<Vec<&str>>()
+                .join(" ");
+
+            for captures in markers.captures_iter(line) {
+                let Some((date, time)) = parse_when(&captures[2]) else {
+                    invalid.push((format!("{note}:{}", index + 1), captures[0].to_string()));
+                    continue;
+                };
+
+                entries.push(Entry {
+                    date,
+                    time,
+                    kind: if &captures[1] == "due" {
+                        Kind::Due
+                    } else {
+                        Kind::Scheduled
+                    },
+                    note: note.clone(),
+                    line: index + 1,
+                    text: if text.is_empty() {
+                        note.clone()
+                    } else {
+                        text.clone()
+                    },
+                });
+            }
+        }
+    }
+
+    entries.sort();
+
+    (entries, invalid)
+}
+
+/// Make overdue items stand out, unless the output isn't a terminal or colour is turned off
+fn highlight(text: &str) -> String {
+    if std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none() {
+        format!("\x1b[1;31m{text}\x1b[0m")
+    } else {
+        text.to_string()
+    }
+}
+
+fn day_heading(date: NaiveDate, today: NaiveDate) -> String {
+    let heading = date.format("%Y-%m-%d %A").to_string();
+
+    match (date - today).num_days() {
+        0 => format!("{heading} (today)"),
+        1 => format!("{heading} (tomorrow)"),
+        _ => heading,
+    }
+}
+
+/// First day after the agenda, or `None` if it goes on past any date which can be written
+fn end_date(today: NaiveDate, days: usize) -> Option<NaiveDate> {
+    today.checked_add_days(Days::new(u64::try_from(days).ok()?))
+}
+
+fn print_agenda(entries: &[Entry], today: NaiveDate, days: usize) {
+    let end = end_date(today, days);
+
+    let overdue = entries
+        .iter()
+        .filter(|entry| entry.date < today)
+        .collect::<Vec<&Entry>>();
+    let upcoming = entries
+        .iter()
+        .filter(|entry| entry.date >= today && end.is_none_or(|end| entry.date < end))
+        .collect::<Vec<&Entry>>();
+
+    if overdue.is_empty() && upcoming.is_empty() {
+        println!("Nothing due or scheduled in the next {days} days");
+        return;
+    }
+
+    if !overdue.is_empty() {
+        println!("{}", highlight("Overdue"));
+
+        for entry in overdue {
+            let late = (today - entry.date).num_days();
+
+            println!(
+                "\t{}",
+                highlight(&format!(
+                    "{}  {:<9}  {}  ({}:{}, {late} {} late)",
+                    entry.date.format(DATE_FORMAT),
+                    entry.kind.as_str(),
+                    entry.text,
+                    entry.note,
+                    entry.line,
+                    if late == 1 { "day" } else { "days" }
+                ))
+            );
+        }
+    }
+
+    let mut current = None;
+
+    for entry in upcoming {
+        if current != Some(entry.date) {
+            println!("{}", day_heading(entry.date, today));
+            current = Some(entry.date);
+        }
+
+        println!(
+            "\t{:<5}  {:<9}  {}  ({}:{})",
+            entry
+                .time
+                .map_or_else(String::new, |time| time.format(TIME_FORMAT).to_string()),
+            entry.kind.as_str(),
+            entry.text,
+            entry.note,
+            entry.line
+        );
+    }
+}
+
+/// Escape text for an iCalendar property value
+fn escape_ics(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Fold a content line so no line is longer than the limit, without splitting characters
+fn fold_ics(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+
+    for c in line.chars() {
+        if length + c.len_utf8() > ICS_LINE_LIMIT {
+            folded.push_str("\r\n ");
+            // The space starting a continuation line counts towards its length
+            length = 1;
+        }
+
+        folded.push(c);
+        length += c.len_utf8();
+    }
+
+    folded.push_str("\r\n");
+    folded
+}
+
+fn render_ics(entries: &[Entry]) -> String {
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//Noteiser//ntsr agenda//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+
+    for entry in entries {
+        // UIDs stay the same when lines move, so re-imports update events instead of copying them
+        let key = format!(
+            "{}\n{}\n{}\n{}",
+            entry.note,
+            entry.kind.as_str(),
+            entry.date,
+            entry.text
+        );
+        let uid = &file::content_hash(key.as_bytes())[..32];
+
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{uid}@ntsr"));
+        lines.push(format!("DTSTAMP:{stamp}"));
+
+        if let Some(time) = entry.time {
+            lines.push(format!(
+                "DTSTART:{}",
+                entry.date.and_time(time).format("%Y%m%dT%H%M%S")
+            ));
+        } else {
+            lines.push(format!(
+                "DTSTART;VALUE=DATE:{}",
+                entry.date.format("%Y%m%d")
+            ));
+            lines.push(format!(
+                "DTEND;VALUE=DATE:{}",
+                (entry.date + Duration::days(1)).format("%Y%m%d")
+            ));
+        }
+
+        lines.push(format!(
+            "SUMMARY:{}",
+            escape_ics(&format!("{}: {}", entry.kind.label(), entry.text))
+        ));
+        lines.push(format!(
+            "DESCRIPTION:{}",
+            escape_ics(&format!("{}, line {}", entry.note, entry.line))
+        ));
+        lines.push(format!("CATEGORIES:{}", entry.kind.label().to_uppercase()));
+        lines.push("TRANSP:TRANSPARENT".to_string());
+        lines.push("END:VEVENT".to_string());
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().fold(String::new(), |mut ics, line| {
+        ics.push_str(&fold_ics(line));
+        ics
+    })
+}
+
+/// Show what's overdue and coming up, or write it as an iCalendar file
+///
+/// Calendars get every open item unless a number of days is given
+pub fn note_agenda(days: Option<usize>, ics: bool, output: Option<&str>) {
+    let dir_path = notes_dir();
+    let today = Local::now().date_naive();
+
+    let (mut entries, invalid) = find_entries(&dir_path);
+
+    if ics {
+        if let Some(end) = days.and_then(|days| end_date(today, days)) {
+            entries.retain(|entry| entry.date < end);
+        }
+
+        let calendar = render_ics(&entries);
+
+        match output {
+            Some(output) => {
+                if let Err(e) = fs::write(output, calendar) {
+                    error!("Could not write '{output}': {e}");
+                }
+
+                println!("Wrote {} events to '{output}'", entries.len());
+            }
+            None => print!("{calendar}"),
+        }
+    } else {
+        print_agenda(&entries, today, days.unwrap_or(14));
+    }
+
+    if !invalid.is_empty() {
+        let mut message = String::from("Ignored markers with invalid dates, expected YYYY-MM-DD:");
+
+        for (location, marker) in invalid {
+            let _ = write!(message, "\n\t{location}  {marker}");
+        }
+
+        // Kept out of the calendar when it's written to stdout
+        eprintln!("{message}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use super::{fold_ics, parse_when, ICS_LINE_LIMIT};
+
+    #[test]
+    fn parses_dates_with_optional_times() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 9);
+        let time = NaiveTime::from_hms_opt(14, 30, 0);
+
+        assert_eq!(parse_when("2024-03-09"), date.map(|date| (date, None)));
+        assert_eq!(
+            parse_when(" 2024-03-09 14:30 "),
+            date.map(|date| (date, time))
+        );
+        assert_eq!(
+            parse_when("2024-03-09T14:30"),
+            date.map(|date| (date, time))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_dates() {
+        assert_eq!(parse_when(""), None);
+        assert_eq!(parse_when("tomorrow"), None);
+        assert_eq!(parse_when("2024-02-30"), None);
+        assert_eq!(parse_when("2024-03-09 25:00"), None);
+        assert_eq!(parse_when("2024-03-09 afternoon"), None);
+    }
+
+    #[test]
+    fn leaves_short_lines_unfolded() {
+        let line = "a".repeat(ICS_LINE_LIMIT);
+
+        assert_eq!(fold_ics(&line), format!("{line}\r\n"));
+    }
+
+    #[test]
+    fn folds_long_lines_within_the_limit() {
+        let line = "a".repeat(ICS_LINE_LIMIT * 3);
+        let folded = fold_ics(&line);
+
+        assert!(folded
+            .split("\r\n")
+            .all(|part| part.len() <= ICS_LINE_LIMIT));
+        assert_eq!(folded.replace("\r\n ", ""), format!("{line}\r\n"));
+        assert!(folded.starts_with(&format!(
+            "{}\r\n {}",
+            "a".repeat(ICS_LINE_LIMIT),
+            "a".repeat(ICS_LINE_LIMIT - 1)
+        )));
+    }
+
+    #[test]
+    fn folds_without_splitting_characters() {
+        // The three byte character would end one byte past the limit, so it starts the next line
+        let line = format!("{}€€", "a".repeat(ICS_LINE_LIMIT - 2));
+        let folded = fold_ics(&line);
+
+        assert_eq!(
+            folded,
+            format!("{}\r\n €€\r\n", "a".repeat(ICS_LINE_LIMIT - 2))
+        );
+
+        let line = "é".repeat(ICS_LINE_LIMIT);
+        let folded = fold_ics(&line);
+
+        assert!(folded
+            .split("\r\n")
+            .all(|part| part.len() <= ICS_LINE_LIMIT));
+        assert_eq!(folded.replace("\r\n ", ""), format!("{line}\r\n"));
+    }
+}
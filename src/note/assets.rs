@@ -0,0 +1,236 @@
+use std::fs;
+use std::path::Path;
+
+use clap::Subcommand;
+
+use super::links::{parent_dir, relative_path};
+use super::{capture, crypt, find_note_exact, git, note_files, notebook, notes_dir};
+use crate::{confirm, error, file, get_all_files, trash, verify_filename};
+
+/// Folder in each notebook which attachments are copied into
+pub const ASSETS_DIR: &str = "assets";
+
+/// Characters of the content hash used to tell attachments apart
+const HASH_PREFIX_LEN: usize = 12;
+
+const IMAGE_EXTENSIONS: [&str; 7] = ["png", "jpg", "jpeg", "gif", "svg", "webp", "bmp"];
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Find attachments which no note links to and offer to remove them
+    Gc,
+}
+
+/// Whether a path relative to the notes directory is inside the assets folder
+#[must_use]
+pub fn is_asset(file_name: &str) -> bool {
+    Path::new(file_name).starts_with(ASSETS_DIR)
+}
+
+/// Copy a file into the assets folder, reusing any attachment with the same contents
+///
+/// Returns the path of the attachment relative to the notes directory
+#[must_use]
+pub fn store(dir_path: &str, source: &str) -> String {
+    let contents = fs::read(source).unwrap_or_else(|e| error!("Could not read '{source}': {e}"));
+
+    let file_name = Path::new(source)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("attachment");
+
+    store_bytes(dir_path, file_name, &contents)
+}
+
+/// Write the contents of an attachment into the assets folder, unless it's already there
+///
+/// Returns the path of the attachment relative to the notes directory. It isn't committed, so
+/// callers storing many attachments can commit them together
+#[must_use]
+pub fn store_bytes(dir_path: &str, file_name: &str, contents: &[u8]) -> String {
+    let hash = file::content_hash(contents);
+    let prefix = &hash[..HASH_PREFIX_LEN];
+
+    let assets_path = format!("{dir_path}/{ASSETS_DIR}");
+
+    if verify_filename(&assets_path).is_some() {
+        if let Some(existing) = get_all_files(&assets_path)
+            .into_iter()
+            .find(|asset| asset.starts_with(&format!("{prefix}-")))
+        {
+            return format!("{ASSETS_DIR}/{existing}");
+        }
+    }
+
+    // Spaces would break the markdown link to the attachment
+    let file_name = file_name.replace(char::is_whitespace, "-");
+    // Attachments exported from ntsr already start with their hash
+    let asset = if file_name.starts_with(&format!("{prefix}-")) {
+        format!("{ASSETS_DIR}/{file_name}")
+    } else {
+        format!("{ASSETS_DIR}/{prefix}-{file_name}")
+    };
+    let asset_path = format!("{dir_path}/{asset}");
+
+    file::create_parent_dirs(&asset_path);
+
+    if let Err(e) = fs::write(&asset_path, contents) {
+        error!("Could not write attachment '{asset_path}': {e}");
+    }
+
+    asset
+}
+
+/// Copy the assets folder into an export, so links to attachments keep working
+///
+/// Returns the number of attachments copied
+#[must_use]
+pub fn copy_to(dir_path: &str, output_dir: &str) -> usize {
+    let assets_path = format!("{dir_path}/{ASSETS_DIR}");
+
+    if verify_filename(&assets_path).is_none() {
+        return 0;
+    }
+
+    let assets = get_all_files(&assets_path);
+
+    for asset in &assets {
+        let destination = format!("{output_dir}/{ASSETS_DIR}/{asset}");
+        file::create_parent_dirs(&destination);
+
+        if let Err(e) = fs::copy(format!("{assets_path}/{asset}"), &destination) {
+            error!("Could not copy '{asset}' to '{destination}': {e}");
+        }
+    }
+
+    assets.len()
+}
+
+/// Link to an attachment from a note, shown inline if it's an image
+#[must_use]
+pub fn markdown_link(note: &str, asset: &str, source: &str) -> String {
+    let name = Path::new(source)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(asset);
+    let target = relative_path(parent_dir(note), asset);
+
+    let is_image = Path::new(asset)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+
+    if is_image {
+        format!("![{name}]({target})")
+    } else {
+        format!("[{name}]({target})")
+    }
+}
+
+pub fn note_attach(name: &str, files: &[String]) {
+    let dir_path = notes_dir();
+    let note = find_note_exact(&dir_path, name);
+    let path = format!("{dir_path}/{note}");
+
+    if crypt::is_encrypted(&path) {
+        error!("Cannot attach files to encrypted note '{note}'");
+    }
+
+    for source in files {
+        if !Path::new(source).is_file() {
+            error!("'{source}' is not a file");
+        }
+    }
+
+    let stored = files
+        .iter()
+        .map(|source| {
+            let asset = store(&dir_path, source);
+            println!("Attached '{source}' as '{asset}'");
+
+            (asset, source)
+        })
+        .collect::<Vec<(String, &String)>>();
+
+    if git::enabled() {
+        let asset_paths = stored
+            .iter()
+            .map(|(asset, _)| format!("{dir_path}/{asset}"))
+            .collect::<Vec<String>>();
+
+        git::commit_files(
+            &notebook::dir_containing(&path),
+            &asset_paths,
+            &format!("attach: {note}"),
+        );
+    }
+
+    let links = stored
+        .iter()
+        .map(|(asset, source)| markdown_link(&note, asset, source))
+        .collect::<Vec<String>>();
+
+    capture::append_to(&path, &capture::entry(&links.join("\n"), false));
+}
+
+fn assets_gc() {
+    let dir_path = notes_dir();
+    let assets_path = format!("{dir_path}/{ASSETS_DIR}");
+
+    if verify_filename(&assets_path).is_none() {
+        println!("No attachments found");
+        return;
+    }
+
+    let notes = note_files(&dir_path);
+
+    if notes.iter().any(|note| crypt::is_encrypted(note)) {
+        println!("Links in encrypted notes can't be read, so they aren't counted");
+    }
+
+    let contents = notes
+        .iter()
+        .filter(|note| !crypt::is_encrypted(note))
+        .filter_map(|note| fs::read_to_string(format!("{dir_path}/{note}")).ok())
+        .collect::<Vec<String>>();
+
+    let unused = get_all_files(&assets_path)
+        .into_iter()
+        .filter(|asset| {
+            !contents
+                .iter()
+                .any(|contents| contents.contains(asset.as_str()))
+        })
+        .collect::<Vec<String>>();
+
+    if unused.is_empty() {
+        println!("Every attachment is linked to by a note");
+        return;
+    }
+
+    println!("Attachments which no note links to:");
+
+    for asset in &unused {
+        let size = fs::metadata(format!("{assets_path}/{asset}")).map_or(0, |m| m.len());
+        println!("\t{size:>8} bytes  {ASSETS_DIR}/{asset}");
+    }
+
+    if !confirm!("move {} unused attachments to the trash", unused.len()) {
+        // User denies confirmation
+        std::process::exit(0x1001);
+    }
+
+    for asset in &unused {
+        let id = trash::move_to_trash(&format!("{assets_path}/{asset}"));
+
+        println!(
+            "Moved {ASSETS_DIR}/{asset} to the trash (restore with 'ntsr trash restore {id}')"
+        );
+    }
+}
+
+pub fn parse_command(command: &Commands) {
+    match command {
+        Commands::Gc => assets_gc(),
+    }
+}
@@ -0,0 +1,75 @@
+use std::fs;
+use std::io::{IsTerminal, Read};
+
+use super::{check_extension, crypt, meta, notes_dir, resolve_note, write_note};
+use crate::{error, file};
+
+/// Read the text to capture from the arguments, or from stdin when there are none
+#[must_use]
+pub fn read_text(words: &[String], from_stdin: bool) -> String {
+    if !words.is_empty() && !from_stdin {
+        return words.join(" ");
+    }
+
+    let mut stdin = std::io::stdin();
+
+    if stdin.is_terminal() {
+        error!("No text given, pass it as arguments or pipe it to stdin");
+    }
+
+    let mut text = String::new();
+    if let Err(e) = stdin.read_to_string(&mut text) {
+        error!("Could not read from stdin: {e}");
+    }
+
+    text
+}
+
+/// Format captured text as an entry, optionally prefixed with the current time
+#[must_use]
+pub fn entry(text: &str, timestamp: bool) -> String {
+    let text = text.trim_end_matches('\n');
+
+    if timestamp {
+        format!("[{}] {text}\n", meta::now())
+    } else {
+        format!("{text}\n")
+    }
+}
+
+/// Add an entry to the end of a note without opening the editor
+pub fn append_to(path: &str, entry: &str) {
+    if crypt::is_encrypted(path) {
+        error!("Cannot append to encrypted note '{path}', open it instead");
+    }
+
+    let before = fs::read_to_string(path).ok();
+
+    let mut after = before.clone().unwrap_or_default();
+    if !after.is_empty() && !after.ends_with('\n') {
+        after.push('\n');
+    }
+    after.push_str(entry);
+
+    write_note(path, before.as_deref(), after);
+}
+
+pub fn note_append(name: &str, words: &[String], timestamp: bool) {
+    let dir_path = notes_dir();
+
+    // Names are not fuzzy matched, so scripts never write to a note they didn't mean to
+    let checked_filename =
+        resolve_note(&dir_path, name).unwrap_or_else(|| check_extension(&name.to_string()));
+    let full_path = format!("{dir_path}/{checked_filename}");
+
+    let text = read_text(words, false);
+    if text.trim().is_empty() {
+        error!("Nothing to append to '{checked_filename}'");
+    }
+
+    file::create(&full_path);
+
+    append_to(&full_path, &entry(&text, timestamp));
+
+    println!("Appended to '{checked_filename}'");
+}
@@ -0,0 +1,314 @@
+use std::fs::{self, DirBuilder, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use zeroize::Zeroizing;
+
+use super::{find_note_exact, git, history, meta, notebook, notes_dir};
+use crate::{confirm, error, get_editor, recent, verify_filename};
+
+/// Extension added to the file name of encrypted notes
+pub const EXTENSION: &str = "enc";
+
+/// Identifies the format of an encrypted note
+const MAGIC: &[u8] = b"NTSRENC1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+#[must_use]
+pub fn is_encrypted(path: &str) -> bool {
+    path.ends_with(&format!(".{EXTENSION}"))
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Zeroizing<[u8; 32]> {
+    let mut key = Zeroizing::new([0_u8; 32]);
+
+    if let Err(e) = Argon2::default().hash_password_into(passphrase.as_bytes(), salt, &mut *key) {
+        error!("Could not derive key from passphrase: {e}");
+    }
+
+    key
+}
+
+fn encrypt(plaintext: &[u8], passphrase: &str) -> Vec<u8> {
+    let mut salt = [0_u8; SALT_LEN];
+    if let Err(e) = OsRng.try_fill_bytes(&mut salt) {
+        error!("Could not generate salt: {e}");
+    }
+
+    let key = derive_key(passphrase, &salt);
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(&*key));
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .unwrap_or_else(|e| error!("Could not encrypt note: {e}"));
+
+    [MAGIC, &salt, nonce.as_slice(), &ciphertext].concat()
+}
+
+/// # Errors
+/// Returns an error if the data isn't an encrypted note, or if the passphrase is wrong
+fn decrypt(data: &[u8], passphrase: &str) -> Result<Zeroizing<Vec<u8>>, String> {
+    let header_len = MAGIC.len() + SALT_LEN + NONCE_LEN;
+
+    if data.len() < header_len || !data.starts_with(MAGIC) {
+        return Err("File is not an encrypted note".to_string());
+    }
+
+    let (salt, rest) = data[MAGIC.len()..].split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+    let key = derive_key(passphrase, salt);
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(&*key));
+
+    cipher
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map(Zeroizing::new)
+        .map_err(|_| "Wrong passphrase or the note has been tampered with".to_string())
+}
+
+fn read_passphrase(confirm_passphrase: bool) -> Zeroizing<String> {
+    let passphrase = Zeroizing::new(
+        rpassword::prompt_password("Passphrase: ")
+            .unwrap_or_else(|e| error!("Could not read passphrase: {e}")),
+    );
+
+    if passphrase.is_empty() {
+        error!("Passphrase cannot be empty");
+    }
+
+    if confirm_passphrase {
+        let repeated = Zeroizing::new(
+            rpassword::prompt_password("Repeat passphrase: ")
+                .unwrap_or_else(|e| error!("Could not read passphrase: {e}")),
+        );
+
+        if passphrase != repeated {
+            error!("Passphrases do not match");
+        }
+    }
+
+    passphrase
+}
+
+/// Write a file by replacing it, so it is never left half written
+fn write_replacing(path: &str, contents: &[u8]) {
+    let temp_path = format!("{path}.tmp");
+
+    let written = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&temp_path, path));
+
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp_path);
+        error!("Could not write encrypted note '{path}': {e}");
+    }
+}
+
+/// Overwrite a file with zeroes before removing it
+fn secure_delete(path: &Path) {
+    if let Ok(metadata) = fs::metadata(path) {
+        let zeroes = vec![0_u8; usize::try_from(metadata.len()).unwrap_or(0)];
+
+        let _ = OpenOptions::new()
+            .write(true)
+            .open(path)
+            .and_then(|mut file| {
+                file.write_all(&zeroes)?;
+                file.sync_all()
+            });
+    }
+
+    if let Err(e) = fs::remove_file(path) {
+        println!("Could not remove plaintext '{}': {e}", path.display());
+    }
+}
+
+/// Create a directory only the current user can access to hold decrypted notes
+fn private_dir() -> PathBuf {
+    let base =
+        std::env::var("XDG_RUNTIME_DIR").map_or_else(|_| std::env::temp_dir(), PathBuf::from);
+    let directory = base.join(format!("ntsr-{}", std::process::id()));
+
+    if let Err(e) = DirBuilder::new().mode(0o700).create(&directory) {
+        error!(
+            "Could not create private directory '{}': {e}",
+            directory.display()
+        );
+    }
+
+    directory
+}
+
+/// Edit plaintext in a private temporary file, returning the edited text
+fn edit_plaintext(file_name: &str, plaintext: &[u8]) -> Zeroizing<Vec<u8>> {
+    let editor = get_editor();
+    let directory = private_dir();
+    let temp_path = directory.join(file_name);
+
+    let written = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&temp_path)
+        .and_then(|mut file| file.write_all(plaintext));
+
+    if let Err(e) = written {
+        let _ = fs::remove_dir(&directory);
+        error!(
+            "Could not write temporary file '{}': {e}",
+            temp_path.display()
+        );
+    }
+
+    // Not run through `run_editor`, so the plaintext isn't recorded as a recent file and is
+    // removed before exiting if the editor fails
+    let failure = match Command::new(&editor).arg(&temp_path).status() {
+        Ok(status) if status.success() => None,
+        Ok(status) => Some(format!(
+            "Editor '{editor}' exited with {status}, the note is unchanged"
+        )),
+        Err(e) => Some(format!("Could not run editor '{editor}': {e}")),
+    };
+
+    if let Some(message) = failure {
+        secure_delete(&temp_path);
+        let _ = fs::remove_dir(&directory);
+        error!("{message}");
+    }
+
+    let edited = fs::read(&temp_path).map(Zeroizing::new);
+
+    secure_delete(&temp_path);
+    let _ = fs::remove_dir(&directory);
+
+    edited.unwrap_or_else(|e| error!("Could not read temporary file: {e}"))
+}
+
+/// Name of the decrypted note, used for the temporary file so editors recognise its type
+fn plain_name(path: &str) -> String {
+    let name = Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("note.txt");
+
+    name.strip_suffix(&format!(".{EXTENSION}"))
+        .unwrap_or(name)
+        .to_string()
+}
+
+fn save_edit(path: &str, before: &[u8], after: &[u8], passphrase: &str) {
+    if before == after {
+        return;
+    }
+
+    let after = String::from_utf8_lossy(after);
+    let after = Zeroizing::new(meta::touch_updated(&after).unwrap_or_else(|| after.to_string()));
+
+    write_replacing(path, &encrypt(after.as_bytes(), passphrase));
+
+    if git::enabled() {
+        git::commit_note(&notebook::dir_containing(path), path);
+    }
+}
+
+/// Decrypt a note into a temporary file, open it in the editor and encrypt the changes
+pub fn edit_encrypted(path: &str) {
+    let data = fs::read(path).unwrap_or_else(|e| error!("Could not read note '{path}': {e}"));
+
+    let passphrase = read_passphrase(false);
+    let plaintext = decrypt(&data, &passphrase).unwrap_or_else(|e| error!("{e}"));
+
+    recent::record(path);
+    let edited = edit_plaintext(&plain_name(path), &plaintext);
+
+    save_edit(path, &plaintext, &edited, &passphrase);
+}
+
+/// Decrypt a note without writing the plaintext anywhere
+#[must_use]
+pub fn read_encrypted(path: &str) -> Zeroizing<String> {
+    let data = fs::read(path).unwrap_or_else(|e| error!("Could not read note '{path}': {e}"));
+
+    let passphrase = read_passphrase(false);
+    let plaintext = decrypt(&data, &passphrase).unwrap_or_else(|e| error!("{e}"));
+
+    Zeroizing::new(String::from_utf8_lossy(&plaintext).into_owned())
+}
+
+/// Create a new encrypted note with some initial contents and open it
+pub fn create_encrypted(path: &str, contents: &str) {
+    if verify_filename(path).is_some() {
+        error!("Note '{path}' already exists");
+    }
+
+    let passphrase = read_passphrase(true);
+
+    write_replacing(path, &encrypt(contents.as_bytes(), &passphrase));
+
+    recent::record(path);
+    let edited = edit_plaintext(&plain_name(path), contents.as_bytes());
+
+    save_edit(path, contents.as_bytes(), &edited, &passphrase);
+}
+
+pub fn note_encrypt(name: &str) {
+    let dir_path = notes_dir();
+    let note = find_note_exact(&dir_path, name);
+    let path = format!("{dir_path}/{note}");
+
+    if is_encrypted(&path) {
+        error!("'{note}' is already encrypted");
+    }
+
+    let encrypted_path = format!("{path}.{EXTENSION}");
+    if verify_filename(&encrypted_path).is_some() {
+        error!("'{note}.{EXTENSION}' already exists");
+    }
+
+    let plaintext =
+        Zeroizing::new(fs::read(&path).unwrap_or_else(|e| error!("Could not read '{path}': {e}")));
+
+    if !confirm!("encrypt {note}, removing the unencrypted note") {
+        // User denies confirmation
+        std::process::exit(0x1001);
+    }
+
+    let passphrase = read_passphrase(true);
+
+    write_replacing(&encrypted_path, &encrypt(&plaintext, &passphrase));
+
+    // Check the note can be read back before removing the original
+    let written = fs::read(&encrypted_path).unwrap_or_default();
+    if decrypt(&written, &passphrase).map_or(true, |decrypted| *decrypted != *plaintext) {
+        error!("Could not verify encrypted note, the original has been kept");
+    }
+
+    secure_delete(Path::new(&path));
+
+    // Snapshots of the note would otherwise still show its plaintext
+    history::forget(&path);
+
+    println!("Encrypted '{note}' to '{note}.{EXTENSION}'");
+
+    if git::enabled() {
+        println!(
+            "Warning: earlier commits in the notes repository still contain the plaintext of '{note}'"
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use std::path::Path;
+
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use clap::Subcommand;
+
+use super::{create_note, edit_note, notes_dir, template};
+use crate::{error, get_files, verify_filename};
+
+const JOURNAL_DIR: &str = "journal";
+const JOURNAL_TEMPLATE: &str = "journal";
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// List the days which have journal entries
+    List {
+        /// Month to list in the form YYYY-MM (defaults to this month)
+        #[clap(short, long, value_parser = parse_month)]
+        month: Option<NaiveDate>,
+    },
+}
+
+/// # Errors
+/// Returns an error if the date is not in the form YYYY-MM-DD
+pub fn parse_date(arg: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(arg, DATE_FORMAT)
+        .map_err(|e| format!("Invalid date '{arg}', expected YYYY-MM-DD: {e}"))
+}
+
+/// # Errors
+/// Returns an error if the month is not in the form YYYY-MM
+pub fn parse_month(arg: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(&format!("{arg}-01"), DATE_FORMAT)
+        .map_err(|e| format!("Invalid month '{arg}', expected YYYY-MM: {e}"))
+}
+
+/// Get the date of the entry to open from the command line options
+#[must_use]
+pub fn entry_date(yesterday: bool, date: Option<NaiveDate>, offset: Option<i64>) -> NaiveDate {
+    let base = date.unwrap_or_else(|| Local::now().date_naive());
+
+    let offset = if yesterday { -1 } else { offset.unwrap_or(0) };
+
+    Duration::try_days(offset)
+        .and_then(|offset| base.checked_add_signed(offset))
+        .unwrap_or_else(|| error!("Offset of {offset} days from {base} is out of range"))
+}
+
+/// Find the journal entries in the journal directory, by date
+fn journal_entries(journal_dir: &str) -> Vec<(NaiveDate, String)> {
+    if verify_filename(journal_dir).is_none() {
+        return Vec::new();
+    }
+
+    let mut entries = get_files(&journal_dir.to_string())
+        .into_iter()
+        .filter_map(|file| {
+            let stem = Path::new(&file).file_stem()?.to_str()?;
+            let date = NaiveDate::parse_from_str(stem, DATE_FORMAT).ok()?;
+
+            Some((date, file))
+        })
+        .collect::<Vec<(NaiveDate, String)>>();
+    entries.sort();
+
+    entries
+}
+
+pub fn note_daily(date: NaiveDate) {
+    let journal_dir = format!("{}/{JOURNAL_DIR}", notes_dir());
+
+    let existing = journal_entries(&journal_dir)
+        .into_iter()
+        .find(|(entry_date, _)| *entry_date == date);
+
+    if let Some((_, file)) = existing {
+        edit_note(&format!("{journal_dir}/{file}"));
+        return;
+    }
+
+    let template_path = template::find(JOURNAL_TEMPLATE);
+    let extension = template_path
+        .as_ref()
+        .and_then(|path| Path::new(path).extension())
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("txt");
+
+    let full_path = format!("{journal_dir}/{}.{extension}", date.format(DATE_FORMAT));
+
+    create_note(&full_path, &[], template_path.as_deref(), &[]);
+
+    edit_note(&full_path);
+}
+
+fn daily_list(month: Option<NaiveDate>) {
+    let month = month.unwrap_or_else(|| {
+        let today = Local::now().date_naive();
+
+        today
+            .with_day(1)
+            .unwrap_or_else(|| error!("Could not get the start of the month"))
+    });
+
+    let journal_dir = format!("{}/{JOURNAL_DIR}", notes_dir());
+
+    let entries = journal_entries(&journal_dir)
+        .into_iter()
+        .filter(|(date, _)| date.year() == month.year() && date.month() == month.month())
+        .collect::<Vec<(NaiveDate, String)>>();
+
+    println!("{}", month.format("%B %Y"));
+
+    if entries.is_empty() {
+        println!("\tNo journal entries");
+        return;
+    }
+
+    for (date, file) in &entries {
+        println!("\t{}  {JOURNAL_DIR}/{file}", date.format("%a %d"));
+    }
+
+    println!("\n{} entries", entries.len());
+}
+
+pub fn parse_command(command: &Commands) {
+    match command {
+        Commands::List { month } => daily_list(*month),
+    }
+}
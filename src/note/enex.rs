@@ -0,0 +1,783 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use md5::{Digest, Md5};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use super::{assets, create_note_with, git, meta, notebook, notes_dir};
+use crate::{error, verify_filename};
+
+/// Format of the timestamps in an Evernote export, which are always in UTC
+const ENEX_DATE_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Elements which only affect how text looks in Evernote, so their text is kept as it is
+const PLAIN_ELEMENTS: [&str; 16] = [
+    "en-note", "span", "font", "u", "sup", "sub", "small", "big", "abbr", "cite", "dfn", "kbd",
+    "samp", "var", "q", "ins",
+];
+
+/// Elements which start a new line, but have no markdown equivalent
+const LINE_ELEMENTS: [&str; 7] = ["div", "center", "address", "caption", "dl", "dt", "dd"];
+
+/// An attachment embedded in a note
+#[derive(Default)]
+struct Resource {
+    /// Base64 encoded contents
+    data: String,
+    mime: String,
+    file_name: Option<String>,
+}
+
+#[derive(Default)]
+struct EnexNote {
+    title: String,
+    created: Option<String>,
+    updated: Option<String>,
+    tags: Vec<String>,
+    /// ENML body of the note
+    content: String,
+    resources: Vec<Resource>,
+}
+
+/// Convert a timestamp from an export into the local time used in front-matter
+fn local_time(timestamp: &str) -> Option<String> {
+    let time = NaiveDateTime::parse_from_str(timestamp.trim(), ENEX_DATE_FORMAT).ok()?;
+
+    Some(meta::format_time(Utc.from_utc_datetime(&time).into()))
+}
+
+fn element_name(element: &BytesStart) -> String {
+    String::from_utf8_lossy(element.local_name().as_ref()).to_lowercase()
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Option<String> {
+    element
+        .attributes()
+        .flatten()
+        .find(|attribute| attribute.key.local_name().as_ref() == name.as_bytes())
+        .and_then(|attribute| attribute.unescape_value().ok())
+        .map(Cow::into_owned)
+}
+
+/// Add text to the field of the last note which `path` points to
+fn add_text(notes: &mut [EnexNote], path: &[String], text: &str) {
+    let Some(note) = notes.last_mut() else {
+        return;
+    };
+
+    let path = path.iter().map(String::as_str).collect::<Vec<&str>>();
+
+    match path.as_slice() {
+        [.., "note", "title"] => note.title.push_str(text),
+        [.., "note", "created"] => note.created = local_time(text),
+        [.., "note", "updated"] => note.updated = local_time(text),
+        [.., "note", "tag"] => note.tags.push(text.trim().to_string()),
+        [.., "note", "content"] => note.content.push_str(text),
+        [.., "resource", "data"] => {
+            if let Some(resource) = note.resources.last_mut() {
+                resource.data.push_str(text);
+            }
+        }
+        [.., "resource", "mime"] => {
+            if let Some(resource) = note.resources.last_mut() {
+                resource.mime = text.trim().to_string();
+            }
+        }
+        [.., "resource-attributes", "file-name"] => {
+            if let Some(resource) = note.resources.last_mut() {
+                resource.file_name = Some(text.trim().to_string());
+            }
+        }
+        _ => {}
+    }
+}
+
+fn parse_enex(contents: &str) -> Result<Vec<EnexNote>, String> {
+    let mut reader = Reader::from_str(contents);
+    reader.trim_text(true);
+
+    let mut notes = Vec::new();
+    let mut path = Vec::new();
+    let mut is_export = false;
+
+    loop {
+        match reader.read_event().map_err(|e| e.to_string())? {
+            Event::Start(element) => {
+                let name = element_name(&element);
+
+                if path.is_empty() {
+                    if name != "en-export" {
+                        return Err(format!("expected <en-export> but found <{name}>"));
+                    }
+
+                    is_export = true;
+                }
+
+                if name == "note" {
+                    notes.push(EnexNote::default());
+                } else if name == "resource" {
+                    if let Some(note) = notes.last_mut() {
+                        note.resources.push(Resource::default());
+                    }
+                }
+
+                path.push(name);
+            }
+            Event::End(_) => {
+                path.pop();
+            }
+            Event::Text(text) => {
+                let text = text.unescape().map_err(|e| e.to_string())?;
+                add_text(&mut notes, &path, &text);
+            }
+            Event::CData(data) => add_text(&mut notes, &path, &String::from_utf8_lossy(&data)),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !is_export {
+        return Err("no <en-export> element found".to_string());
+    }
+
+    Ok(notes)
+}
+
+/// Name for an attachment which wasn't given one, from its type
+fn resource_file_name(resource: &Resource) -> String {
+    let extension = resource
+        .mime
+        .split_once('/')
+        .map(|(_, subtype)| subtype)
+        .filter(|subtype| !subtype.is_empty() && subtype.chars().all(char::is_alphanumeric))
+        .unwrap_or("bin");
+
+    format!("attachment.{extension}")
+}
+
+/// Converts the ENML body of a note into markdown, noting anything it can't convert
+struct Converter<'a> {
+    /// Markdown links to the note's attachments, by the MD5 hash of their contents
+    media: &'a BTreeMap<String, String>,
+    markdown: String,
+    /// Elements which are open, or what they're treated as
+    open: Vec<String>,
+    /// Next number of each open list, or `None` if it's unordered
+    lists: Vec<Option<usize>>,
+    links: Vec<String>,
+    quote_depth: usize,
+    code_depth: usize,
+    /// Depth of encrypted sections, which are left out
+    skip_depth: usize,
+    /// Whether a list item has been started with no text in it yet
+    item_started: bool,
+    in_cell: bool,
+    table_rows: usize,
+    row_cells: usize,
+    used_media: BTreeSet<String>,
+    unconverted: BTreeSet<String>,
+}
+
+impl<'a> Converter<'a> {
+    const fn new(media: &'a BTreeMap<String, String>) -> Self {
+        Self {
+            media,
+            markdown: String::new(),
+            open: Vec::new(),
+            lists: Vec::new(),
+            links: Vec::new(),
+            quote_depth: 0,
+            code_depth: 0,
+            skip_depth: 0,
+            item_started: false,
+            in_cell: false,
+            table_rows: 0,
+            row_cells: 0,
+            used_media: BTreeSet::new(),
+            unconverted: BTreeSet::new(),
+        }
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.markdown.is_empty() || self.markdown.ends_with('\n')
+    }
+
+    fn push(&mut self, text: &str) {
+        if text.is_empty() || self.skip_depth > 0 {
+            return;
+        }
+
+        if self.at_line_start() {
+            for _ in 0..self.quote_depth {
+                self.markdown.push_str("> ");
+            }
+        }
+
+        self.markdown.push_str(text);
+        self.item_started = false;
+    }
+
+    fn trim_spaces(&mut self) {
+        let trimmed = self.markdown.trim_end_matches(' ').len();
+        self.markdown.truncate(trimmed);
+    }
+
+    /// Start a new line, unless one was just started
+    fn newline(&mut self) {
+        if self.in_cell {
+            self.push(" ");
+        } else if !self.at_line_start() && !self.item_started {
+            self.trim_spaces();
+            self.markdown.push('\n');
+        }
+    }
+
+    fn line_break(&mut self) {
+        if self.in_cell {
+            self.push(" ");
+        } else {
+            self.trim_spaces();
+            self.markdown.push('\n');
+        }
+    }
+
+    /// Leave a blank line, unless there is one already
+    fn paragraph(&mut self) {
+        self.newline();
+
+        if !self.in_cell && !self.markdown.is_empty() && !self.markdown.ends_with("\n\n") {
+            self.markdown.push('\n');
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.code_depth > 0 {
+            self.push(text);
+            return;
+        }
+
+        // Whitespace is collapsed the way a browser would show it
+        let mut collapsed = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+        if text.starts_with(char::is_whitespace)
+            && !self.at_line_start()
+            && !self.item_started
+            && !self.markdown.ends_with(' ')
+        {
+            collapsed.insert(0, ' ');
+        }
+
+        if text.ends_with(char::is_whitespace) && !text.trim().is_empty() {
+            collapsed.push(' ');
+        }
+
+        self.push(&collapsed);
+    }
+
+    fn start_code_block(&mut self) {
+        self.paragraph();
+        self.push("```");
+        self.markdown.push('\n');
+        self.code_depth += 1;
+    }
+
+    fn end_code_block(&mut self) {
+        self.code_depth = self.code_depth.saturating_sub(1);
+        self.newline();
+        self.push("```");
+        self.paragraph();
+    }
+
+    fn media(&mut self, element: &BytesStart) {
+        let hash = attribute(element, "hash").unwrap_or_default();
+
+        match self.media.get(&hash) {
+            Some(link) => {
+                let link = link.clone();
+                self.push(&link);
+                self.used_media.insert(hash);
+            }
+            None => {
+                self.unconverted
+                    .insert(format!("missing attachment with hash '{hash}'"));
+            }
+        }
+    }
+
+    fn todo(&mut self, element: &BytesStart) {
+        let checked = attribute(element, "checked").is_some_and(|checked| checked == "true");
+        let checkbox = if checked { "[x] " } else { "[ ] " };
+
+        // Checkboxes outside of lists need to be list items to be read as to-dos
+        if self.lists.is_empty() {
+            self.newline();
+            self.push("- ");
+        }
+
+        self.push(checkbox);
+    }
+
+    fn list_item(&mut self) {
+        self.newline();
+
+        let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+        let marker = match self.lists.last_mut() {
+            Some(Some(number)) => {
+                *number += 1;
+                format!("{}. ", *number - 1)
+            }
+            _ => "- ".to_string(),
+        };
+
+        self.push(&format!("{indent}{marker}"));
+        self.item_started = true;
+    }
+
+    fn start(&mut self, element: &BytesStart) {
+        let name = element_name(element);
+
+        let is_code_block = name == "div"
+            && attribute(element, "style")
+                .is_some_and(|style| style.replace(' ', "").contains("-en-codeblock:true"));
+
+        match name.as_str() {
+            _ if is_code_block => {
+                self.start_code_block();
+                self.open.push("pre".to_string());
+                return;
+            }
+            "pre" => self.start_code_block(),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.paragraph();
+                let level = name[1..].parse().unwrap_or(1);
+                self.push(&format!("{} ", "#".repeat(level)));
+            }
+            "p" => self.paragraph(),
+            "br" => self.line_break(),
+            "hr" => {
+                self.paragraph();
+                self.push("---");
+                self.paragraph();
+            }
+            "b" | "strong" => self.push("**"),
+            "i" | "em" => self.push("*"),
+            "s" | "strike" | "del" => self.push("~~"),
+            "code" | "tt" if self.code_depth == 0 => self.push("`"),
+            "a" => {
+                let Some(href) = attribute(element, "href") else {
+                    // Anchors without a link are left as plain text
+                    self.open.push("span".to_string());
+                    return;
+                };
+
+                self.push("[");
+                self.links.push(href);
+            }
+            "img" => {
+                let source = attribute(element, "src").unwrap_or_default();
+                let alt = attribute(element, "alt").unwrap_or_default();
+                self.push(&format!("![{alt}]({source})"));
+            }
+            "ul" | "ol" => {
+                if self.lists.is_empty() {
+                    self.paragraph();
+                }
+
+                self.lists.push((name == "ol").then_some(1));
+            }
+            "li" => self.list_item(),
+            "blockquote" => {
+                self.paragraph();
+                self.quote_depth += 1;
+            }
+            "table" => {
+                self.paragraph();
+                self.table_rows = 0;
+            }
+            "tr" => {
+                self.newline();
+                self.push("|");
+                self.row_cells = 0;
+            }
+            "td" | "th" => {
+                self.push(" ");
+                self.in_cell = true;
+            }
+            // Code inside a code block is already shown as code
+            "code" | "tt" | "tbody" | "thead" | "tfoot" | "colgroup" | "col" => {}
+            "en-media" => self.media(element),
+            "en-todo" => self.todo(element),
+            "en-crypt" => {
+                self.unconverted.insert("encrypted text".to_string());
+                self.skip_depth += 1;
+            }
+            _ if LINE_ELEMENTS.contains(&name.as_str()) => self.newline(),
+            _ if PLAIN_ELEMENTS.contains(&name.as_str()) => {}
+            _ => {
+                self.unconverted
+                    .insert(format!("<{name}> elements, only their text was kept"));
+            }
+        }
+
+        self.open.push(name);
+    }
+
+    fn end(&mut self) {
+        let Some(name) = self.open.pop() else {
+            return;
+        };
+
+        match name.as_str() {
+            "pre" => self.end_code_block(),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "p" | "table" => self.paragraph(),
+            "b" | "strong" => self.push("**"),
+            "i" | "em" => self.push("*"),
+            "s" | "strike" | "del" => self.push("~~"),
+            "code" | "tt" if self.code_depth == 0 => self.push("`"),
+            "a" => {
+                let href = self.links.pop().unwrap_or_default();
+                self.push(&format!("]({href})"));
+            }
+            "ul" | "ol" => {
+                self.lists.pop();
+
+                if self.lists.is_empty() {
+                    self.paragraph();
+                } else {
+                    self.newline();
+                }
+            }
+            "li" => {
+                self.item_started = false;
+                self.newline();
+            }
+            "blockquote" => {
+                self.newline();
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+                self.paragraph();
+            }
+            "tr" => {
+                self.markdown.push('\n');
+
+                // The first row is used as the header
+                if self.table_rows == 0 {
+                    self.push(&format!("|{}", "---|".repeat(self.row_cells.max(1))));
+                    self.markdown.push('\n');
+                }
+
+                self.table_rows += 1;
+            }
+            "td" | "th" => {
+                self.in_cell = false;
+                self.trim_spaces();
+                self.push(" |");
+                self.row_cells += 1;
+            }
+            "en-crypt" => self.skip_depth = self.skip_depth.saturating_sub(1),
+            _ if LINE_ELEMENTS.contains(&name.as_str()) => self.newline(),
+            _ => {}
+        }
+    }
+
+    fn convert(mut self, enml: &str) -> (String, BTreeSet<String>, BTreeSet<String>) {
+        let mut reader = Reader::from_str(enml);
+        // Evernote isn't always strict about closing elements
+        reader.check_end_names(false);
+
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(element)) => self.start(&element),
+                Ok(Event::Empty(element)) => {
+                    self.start(&element);
+                    self.end();
+                }
+                Ok(Event::End(_)) => self.end(),
+                Ok(Event::Text(text)) => match text.unescape() {
+                    Ok(text) => self.text(&text),
+                    Err(e) => {
+                        self.unconverted.insert(format!("text with {e}"));
+                        self.text(&String::from_utf8_lossy(&text));
+                    }
+                },
+                Ok(Event::CData(data)) => self.text(&String::from_utf8_lossy(&data)),
+                Ok(Event::Eof) => break,
+                Ok(_) => {}
+                Err(e) => {
+                    self.unconverted.insert(format!(
+                        "everything after position {} of the body ({e})",
+                        reader.buffer_position()
+                    ));
+                    break;
+                }
+            }
+        }
+
+        let mut markdown = self.markdown.trim_end().to_string();
+        markdown.push('\n');
+
+        (markdown, self.used_media, self.unconverted)
+    }
+}
+
+/// File name for a note from its title, which mustn't clash with any note before it in the export
+///
+/// Notes are saved as markdown, since that is what their body is converted into
+fn note_file_name(title: &str, taken: &BTreeSet<String>) -> String {
+    let name = title
+        .replace(['/', '\\'], "-")
+        .trim()
+        .trim_start_matches('.')
+        .to_string();
+    let name = if name.is_empty() {
+        "Untitled".to_string()
+    } else {
+        name
+    };
+
+    let mut file_name = format!("{name}.md");
+    let mut count = 1;
+
+    while taken.contains(&file_name) {
+        count += 1;
+        file_name = format!("{name} {count}.md");
+    }
+
+    file_name
+}
+
+pub fn note_import(source: &str) {
+    let contents =
+        fs::read_to_string(source).unwrap_or_else(|e| error!("Could not read '{source}': {e}"));
+
+    let notes = parse_enex(&contents)
+        .unwrap_or_else(|e| error!("Could not parse '{source}' as an Evernote export: {e}"));
+
+    let dir_path = notes_dir();
+
+    let mut taken = BTreeSet::new();
+    let mut imported = 0;
+    let mut conflicts = Vec::new();
+    let mut problems = Vec::new();
+    let mut attachments = 0;
+    // Everything is committed at the end, so an import is a single commit
+    let mut written = Vec::new();
+
+    for note in notes {
+        let file_name = note_file_name(&note.title, &taken);
+        let full_path = format!("{dir_path}/{file_name}");
+        taken.insert(file_name.clone());
+
+        if verify_filename(&full_path).is_some() {
+            conflicts.push(file_name);
+            continue;
+        }
+
+        let mut media = BTreeMap::new();
+
+        for resource in &note.resources {
+            let name = resource
+                .file_name
+                .clone()
+                .unwrap_or_else(|| resource_file_name(resource));
+
+            let data = resource
+                .data
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect::<String>();
+
+            let Ok(bytes) = base64::decode(data) else {
+                problems.push((
+                    file_name.clone(),
+                    format!("attachment '{name}' is not valid"),
+                ));
+                continue;
+            };
+
+            let hash = format!("{:x}", Md5::digest(&bytes));
+
+            let asset = assets::store_bytes(&dir_path, &name, &bytes);
+            written.push(format!("{dir_path}/{asset}"));
+            media.insert(hash, assets::markdown_link(&file_name, &asset, &name));
+            attachments += 1;
+        }
+
+        let (mut body, used_media, unconverted) = Converter::new(&media).convert(&note.content);
+
+        // Attachments which the body doesn't show are linked at the end
+        for (hash, link) in &media {
+            if !used_media.contains(hash) {
+                body.push('\n');
+                body.push_str(link);
+                body.push('\n');
+            }
+        }
+
+        let front_matter = meta::FrontMatter {
+            title: Some(note.title.trim().to_string()),
+            created: note.created,
+            updated: note.updated,
+            tags: note.tags,
+        };
+
+        create_note_with(&full_path, &format!("{}{body}", front_matter.render()));
+        written.push(full_path);
+
+        problems.extend(
+            unconverted
+                .into_iter()
+                .map(|problem| (file_name.clone(), problem)),
+        );
+        imported += 1;
+    }
+
+    if git::enabled() {
+        git::commit_files(
+            &notebook::dir_containing(&dir_path),
+            &written,
+            &format!("import: {source}"),
+        );
+    }
+
+    println!("Imported {imported} notes and {attachments} attachments from '{source}'");
+
+    if !conflicts.is_empty() {
+        println!(
+            "Skipped {} notes which already exist in '{dir_path}':",
+            conflicts.len()
+        );
+
+        for conflict in conflicts {
+            println!("\t{conflict}");
+        }
+    }
+
+    if !problems.is_empty() {
+        println!("Some content could not be converted:");
+
+        for (note, problem) in problems {
+            println!("\t{note}: {problem}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use super::{note_file_name, Converter};
+
+    fn convert(enml: &str) -> String {
+        let media = BTreeMap::new();
+        let (markdown, _, unconverted) = Converter::new(&media).convert(enml);
+
+        assert!(unconverted.is_empty(), "unconverted: {unconverted:?}");
+        markdown
+    }
+
+    #[test]
+    fn converts_text_formatting() {
+        assert_eq!(
+            convert(
+                "<en-note><h2>Title</h2><p>Some <b>bold</b>,  <i>italic</i>\n and \
+                 <a href=\"https://example.com\">linked</a> text</p><div>Next<br/>line</div></en-note>"
+            ),
+            "## Title\n\nSome **bold**, *italic* and [linked](https://example.com) text\n\nNext\nline\n"
+        );
+    }
+
+    #[test]
+    fn converts_nested_lists() {
+        assert_eq!(
+            convert(
+                "<en-note><ul><li>One</li><li>Two<ol><li>First</li><li>Second</li></ol></li>\
+                 <li>Three</li></ul></en-note>"
+            ),
+            "- One\n- Two\n  1. First\n  2. Second\n- Three\n"
+        );
+    }
+
+    #[test]
+    fn converts_todos() {
+        assert_eq!(
+            convert(
+                "<en-note><div><en-todo checked=\"true\"/>Done</div>\
+                 <div><en-todo checked=\"false\"/>Open</div></en-note>"
+            ),
+            "- [x] Done\n- [ ] Open\n"
+        );
+        assert_eq!(
+            convert("<en-note><ul><li><en-todo/>In a list</li></ul></en-note>"),
+            "- [ ] In a list\n"
+        );
+    }
+
+    #[test]
+    fn converts_tables() {
+        assert_eq!(
+            convert(
+                "<en-note><table><tr><th>Name</th><th>Count</th></tr>\
+                 <tr><td>Apples</td><td>3<br/>or 4</td></tr></table></en-note>"
+            ),
+            "| Name | Count |\n|---|---|\n| Apples | 3 or 4 |\n"
+        );
+    }
+
+    #[test]
+    fn keeps_code_blocks_as_they_are() {
+        assert_eq!(
+            convert(
+                "<en-note><p>Before</p><div style=\"-en-codeblock: true\">\
+                 <div>fn main() {</div><div>    x  =  1;</div><div>}</div></div></en-note>"
+            ),
+            "Before\n\n```\nfn main() {\n    x  =  1;\n}\n```\n"
+        );
+    }
+
+    #[test]
+    fn links_media_and_reports_what_it_cannot_convert() {
+        let media = BTreeMap::from([(
+            "0123abcd".to_string(),
+            "![photo](assets/photo.png)".to_string(),
+        )]);
+
+        let (markdown, used_media, unconverted) = Converter::new(&media).convert(
+            "<en-note><div><en-media hash=\"0123abcd\" type=\"image/png\"/></div>\
+             <div><en-media hash=\"ffff\" type=\"image/png\"/></div>\
+             <div>Visible<en-crypt>c2VjcmV0</en-crypt></div></en-note>",
+        );
+
+        assert_eq!(markdown, "![photo](assets/photo.png)\nVisible\n");
+        assert_eq!(used_media, BTreeSet::from(["0123abcd".to_string()]));
+        assert_eq!(
+            unconverted,
+            BTreeSet::from([
+                "encrypted text".to_string(),
+                "missing attachment with hash 'ffff'".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn quotes_blockquotes() {
+        assert_eq!(
+            convert(
+                "<en-note><blockquote><div>Quoted</div><div>lines</div></blockquote></en-note>"
+            ),
+            "> Quoted\n> lines\n"
+        );
+    }
+
+    #[test]
+    fn file_names_do_not_clash() {
+        let taken = BTreeSet::from(["Plans.md".to_string(), "Plans 2.md".to_string()]);
+
+        assert_eq!(note_file_name("Plans", &taken), "Plans 3.md");
+        assert_eq!(note_file_name("a/b", &taken), "a-b.md");
+        assert_eq!(note_file_name(" .hidden", &taken), "hidden.md");
+        assert_eq!(note_file_name("", &taken), "Untitled.md");
+    }
+}
@@ -0,0 +1,279 @@
+use std::fmt::Write;
+use std::fs;
+
+use clap::ValueEnum;
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+use regex::Captures;
+
+use super::links::{is_markdown, parent_dir, relative_path, strip_extension, wiki_link_regex};
+use super::{assets, crypt, find_note, meta, note_files, notes_dir, resolve_note, vault};
+use crate::{error, file};
+
+const STYLESHEET: &str = "
+body { max-width: 48em; margin: 2em auto; padding: 0 1em; font-family: sans-serif; line-height: 1.5; color: #222; }
+h1, h2, h3, h4, h5, h6 { line-height: 1.2; }
+a { color: #0366d6; }
+pre, code { font-family: monospace; background: #f4f4f4; border-radius: 3px; }
+pre { padding: 0.75em; overflow-x: auto; }
+code { padding: 0.1em 0.3em; }
+pre code { padding: 0; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; }
+blockquote { margin-left: 0; padding-left: 1em; border-left: 3px solid #ccc; color: #555; }
+.tags span { display: inline-block; margin-right: 0.4em; padding: 0 0.4em; border-radius: 3px; background: #eee; font-size: 0.9em; }
+";
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Html,
+    /// Markdown with YAML front-matter, which can be opened as an Obsidian vault
+    Obsidian,
+}
+
+#[must_use]
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Path of the exported page of a note
+fn page_name(file_name: &str) -> String {
+    format!("{}.html", strip_extension(file_name))
+}
+
+/// Turn `[[wiki-links]]` into markdown links to the exported pages
+fn convert_wiki_links(dir_path: &str, file_name: &str, body: &str) -> String {
+    wiki_link_regex()
+        .replace_all(body, |captures: &Captures| {
+            // Notes can't be embedded in a page, so embeds become links too
+            let target = captures[2].trim();
+            let anchor = captures.get(3).map_or("", |m| m.as_str());
+            let text = captures.get(4).map_or(target, |m| m.as_str());
+
+            resolve_note(dir_path, target).map_or_else(
+                || text.to_string(),
+                |resolved| {
+                    format!(
+                        "[{text}]({}{anchor})",
+                        relative_path(parent_dir(file_name), &page_name(&resolved))
+                    )
+                },
+            )
+        })
+        .into_owned()
+}
+
+fn render_markdown(body: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+
+    // Relative links to other notes point to their exported pages instead
+    let parser = Parser::new_ext(body, options).map(|event| match event {
+        Event::Start(Tag::Link(link_type, destination, title))
+            if !destination.contains("://")
+                && is_markdown(destination.split('#').next().unwrap_or("")) =>
+        {
+            let (path, anchor) = destination.split_once('#').map_or_else(
+                || (destination.as_ref(), String::new()),
+                |(path, anchor)| (path, format!("#{anchor}")),
+            );
+
+            Event::Start(Tag::Link(
+                link_type,
+                CowStr::from(format!("{}{anchor}", page_name(path))),
+                title,
+            ))
+        }
+        other => other,
+    });
+
+    let mut rendered = String::new();
+    html::push_html(&mut rendered, parser);
+
+    rendered
+}
+
+fn page(title: &str, content: &str) -> String {
+    format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">
+<title>{}</title>
+<style>{STYLESHEET}</style>
+</head>
+<body>
+{content}</body>
+</html>
+",
+        escape_html(title)
+    )
+}
+
+/// Render a note as a standalone HTML page
+fn render_note(dir_path: &str, file_name: &str, contents: &str) -> String {
+    let front_matter = meta::parse(contents);
+    let (_, body) = meta::split(contents);
+
+    let title = front_matter
+        .as_ref()
+        .and_then(|front_matter| front_matter.title.clone())
+        .unwrap_or_else(|| strip_extension(file_name).to_string());
+
+    let mut content = String::new();
+
+    if let Some(front_matter) = &front_matter {
+        if !front_matter.tags.is_empty() {
+            content.push_str("<p class=\"tags\">");
+
+            for tag in &front_matter.tags {
+                let _ = write!(content, "<span>{}</span>", escape_html(tag));
+            }
+
+            content.push_str("</p>\n");
+        }
+    }
+
+    if is_markdown(file_name) {
+        content.push_str(&render_markdown(&convert_wiki_links(
+            dir_path, file_name, body,
+        )));
+    } else {
+        let _ = writeln!(content, "<pre>{}</pre>", escape_html(body));
+    }
+
+    page(&title, &content)
+}
+
+fn write_file(path: &String, contents: &str) {
+    file::create_parent_dirs(path);
+
+    if let Err(e) = fs::write(path, contents) {
+        error!("Could not write '{path}': {e}");
+    }
+}
+
+fn export_all(dir_path: &str, output_dir: &str) {
+    let mut exported = 0;
+    let mut skipped = Vec::new();
+
+    let mut index = String::from("<h1>Notes</h1>\n<ul>\n");
+
+    for note in note_files(dir_path) {
+        if crypt::is_encrypted(&note) {
+            skipped.push(note);
+            continue;
+        }
+
+        let Ok(contents) = fs::read_to_string(format!("{dir_path}/{note}")) else {
+            skipped.push(note);
+            continue;
+        };
+
+        let page_path = page_name(&note);
+
+        write_file(
+            &format!("{output_dir}/{page_path}"),
+            &render_note(dir_path, &note, &contents),
+        );
+
+        let _ = writeln!(
+            index,
+            "<li><a href=\"{}\">{}</a></li>",
+            escape_html(&page_path),
+            escape_html(strip_extension(&note))
+        );
+
+        exported += 1;
+    }
+
+    index.push_str("</ul>\n");
+
+    write_file(&format!("{output_dir}/index.html"), &page("Notes", &index));
+
+    let attachments = assets::copy_to(dir_path, output_dir);
+
+    println!("Exported {exported} notes and {attachments} attachments to '{output_dir}'");
+
+    if !skipped.is_empty() {
+        println!("Skipped {} encrypted or unreadable notes:", skipped.len());
+
+        for note in skipped {
+            println!("\t{note}");
+        }
+    }
+}
+
+pub fn note_export(file_name: Option<&str>, format: Format, output: Option<&str>, all: bool) {
+    let dir_path = notes_dir();
+
+    match format {
+        Format::Html => {
+            if all {
+                let output_dir =
+                    output.unwrap_or_else(|| error!("An output directory is needed with --all"));
+
+                export_all(&dir_path, output_dir);
+                return;
+            }
+
+            let name = file_name.unwrap_or_else(|| error!("No note given to export"));
+            let note = find_note(&dir_path, name);
+
+            if crypt::is_encrypted(&note) {
+                error!("Cannot export encrypted note '{note}'");
+            }
+
+            let contents = fs::read_to_string(format!("{dir_path}/{note}"))
+                .unwrap_or_else(|e| error!("Could not read note '{note}': {e}"));
+
+            let html = render_note(&dir_path, &note, &contents);
+
+            match output {
+                Some(output) => {
+                    write_file(&output.to_string(), &html);
+                    println!("Exported '{note}' to '{output}'");
+                }
+                None => print!("{html}"),
+            }
+        }
+        Format::Obsidian => {
+            if all {
+                let output_dir =
+                    output.unwrap_or_else(|| error!("An output directory is needed with --all"));
+
+                vault::export_vault(&dir_path, output_dir);
+                return;
+            }
+
+            let name = file_name.unwrap_or_else(|| error!("No note given to export"));
+            let note = find_note(&dir_path, name);
+
+            let contents = fs::read_to_string(format!("{dir_path}/{note}"))
+                .unwrap_or_else(|e| error!("Could not read note '{note}': {e}"));
+            let markdown = vault::to_obsidian(&contents);
+
+            let left_out = meta::other_keys(&contents);
+            if !left_out.is_empty() {
+                // Kept out of the note when it's written to stdout
+                eprintln!(
+                    "Could not export front-matter keys of '{note}': {}",
+                    left_out.join(", ")
+                );
+            }
+
+            match output {
+                Some(output) => {
+                    write_file(&output.to_string(), &markdown);
+                    println!("Exported '{note}' to '{output}'");
+                }
+                None => print!("{markdown}"),
+            }
+        }
+    }
+}
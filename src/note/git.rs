@@ -0,0 +1,173 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+use super::{find_note, notes_dir};
+use crate::commands::run_command;
+use crate::{config, error, verify_filename};
+
+/// Whether notes should be committed to git after they are edited
+#[must_use]
+pub fn enabled() -> bool {
+    config::get()
+        .ok()
+        .and_then(|config| {
+            config
+                .note
+                .as_ref()
+                .and_then(config::Note::settings)
+                .and_then(|settings| settings.git)
+        })
+        .unwrap_or(false)
+}
+
+fn git(dir_path: &str, args: &[&str]) -> Output {
+    match Command::new("git")
+        .arg("-C")
+        .arg(dir_path)
+        .args(args)
+        .output()
+    {
+        Ok(output) => output,
+        Err(e) => error!("Could not run git with args: {:?}\n Error: {e}", args),
+    }
+}
+
+fn git_checked(dir_path: &str, args: &[&str]) {
+    let output = git(dir_path, args);
+
+    if !output.status.success() {
+        error!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+}
+
+fn ensure_repository(dir_path: &str) {
+    if verify_filename(&format!("{dir_path}/.git")).is_none() {
+        git_checked(dir_path, &["init", "--quiet"]);
+
+        println!("Initialised a git repository in '{dir_path}'");
+    }
+}
+
+/// Path of a file relative to the repository it's in
+fn repository_path(dir_path: &str, path: &str) -> String {
+    Path::new(path)
+        .strip_prefix(dir_path)
+        .ok()
+        .and_then(|relative| relative.to_str())
+        .unwrap_or(path)
+        .trim_start_matches('/')
+        .to_string()
+}
+
+fn is_tracked(dir_path: &str, file_name: &str) -> bool {
+    git(dir_path, &["ls-files", "--error-unmatch", "--", file_name])
+        .status
+        .success()
+}
+
+/// Commit a note if it has changed since it was last committed
+pub fn commit_note(dir_path: &str, path: &str) {
+    let file_name = repository_path(dir_path, path);
+
+    ensure_repository(dir_path);
+
+    let tracked = is_tracked(dir_path, &file_name);
+
+    git_checked(dir_path, &["add", "--", &file_name]);
+
+    // `diff --quiet` exits successfully when there is nothing to commit
+    if git(dir_path, &["diff", "--cached", "--quiet", "--", &file_name])
+        .status
+        .success()
+    {
+        return;
+    }
+
+    let message = format!("{}: {file_name}", if tracked { "edit" } else { "create" });
+
+    git_checked(
+        dir_path,
+        &["commit", "--quiet", "-m", &message, "--", &file_name],
+    );
+}
+
+/// Commit many files at once, such as everything written by an import, if any have changed
+///
+/// Paths of files which were removed can be given too, so moves are committed as a whole
+pub fn commit_files(dir_path: &str, paths: &[String], message: &str) {
+    // Removed files which were never committed have nothing to record
+    let file_names = paths
+        .iter()
+        .filter(|path| {
+            Path::new(path).exists() || is_tracked(dir_path, &repository_path(dir_path, path))
+        })
+        .map(|path| repository_path(dir_path, path))
+        .collect::<Vec<String>>();
+
+    if file_names.is_empty() {
+        return;
+    }
+
+    ensure_repository(dir_path);
+
+    let mut add = vec!["add", "--all", "--"];
+    add.extend(file_names.iter().map(String::as_str));
+    git_checked(dir_path, &add);
+
+    let mut diff = vec!["diff", "--cached", "--quiet", "--"];
+    diff.extend(file_names.iter().map(String::as_str));
+
+    if git(dir_path, &diff).status.success() {
+        return;
+    }
+
+    let mut commit = vec!["commit", "--quiet", "-m", message, "--"];
+    commit.extend(file_names.iter().map(String::as_str));
+    git_checked(dir_path, &commit);
+}
+
+fn note_repository(name: &str) -> (String, String) {
+    let dir_path = notes_dir();
+
+    if verify_filename(&format!("{dir_path}/.git")).is_none() {
+        error!("Notes directory '{dir_path}' is not a git repository, set 'note.git = true' in the config");
+    }
+
+    let note = find_note(&dir_path, name);
+
+    (dir_path, note)
+}
+
+pub fn note_log(name: &str) {
+    let (dir_path, note) = note_repository(name);
+
+    run_command(
+        "git",
+        &vec![
+            "-C",
+            &dir_path,
+            "log",
+            "--follow",
+            "--format=%h  %ad  %s",
+            "--date=format:%Y-%m-%d %H:%M",
+            "--",
+            &note,
+        ],
+    );
+}
+
+pub fn note_diff(name: &str, rev: Option<&str>) {
+    let (dir_path, note) = note_repository(name);
+
+    match rev {
+        Some(rev) => run_command("git", &vec!["-C", &dir_path, "diff", rev, "--", &note]),
+        None => run_command(
+            "git",
+            &vec!["-C", &dir_path, "log", "-p", "-1", "--follow", "--", &note],
+        ),
+    }
+}
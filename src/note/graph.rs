@@ -0,0 +1,206 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::Write;
+use std::fs;
+
+use clap::ValueEnum;
+use serde_derive::Serialize;
+
+use super::links::{linked_notes, strip_extension};
+use super::{crypt, find_note, meta, note_files, notes_dir};
+use crate::error;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Dot,
+    Json,
+}
+
+#[derive(Serialize)]
+struct Node {
+    /// Path of the note relative to the notes directory
+    id: String,
+    title: String,
+    tags: Vec<String>,
+    created: Option<String>,
+    updated: Option<String>,
+    /// Whether the note has no links to or from any other note
+    orphan: bool,
+}
+
+#[derive(Serialize, PartialEq, Eq, PartialOrd, Ord)]
+struct Edge {
+    source: String,
+    target: String,
+}
+
+#[derive(Serialize)]
+struct Graph {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+}
+
+fn build_graph(dir_path: &str) -> Graph {
+    let mut nodes = Vec::new();
+    let mut edges = BTreeSet::new();
+
+    for file_name in note_files(dir_path) {
+        // Encrypted notes are included, but their links can't be read
+        let contents = if crypt::is_encrypted(&file_name) {
+            None
+        } else {
+            fs::read_to_string(format!("{dir_path}/{file_name}")).ok()
+        };
+
+        let front_matter = contents
+            .as_deref()
+            .and_then(meta::parse)
+            .unwrap_or_default();
+
+        for target in contents
+            .as_deref()
+            .map(|contents| linked_notes(dir_path, &file_name, contents))
+            .unwrap_or_default()
+        {
+            if target != file_name {
+                edges.insert(Edge {
+                    source: file_name.clone(),
+                    target,
+                });
+            }
+        }
+
+        nodes.push(Node {
+            title: front_matter
+                .title
+                .unwrap_or_else(|| strip_extension(&file_name).to_string()),
+            tags: front_matter.tags,
+            created: front_matter.created,
+            updated: front_matter.updated,
+            orphan: false,
+            id: file_name,
+        });
+    }
+
+    let linked = edges
+        .iter()
+        .flat_map(|edge| [&edge.source, &edge.target])
+        .collect::<BTreeSet<&String>>();
+
+    for node in &mut nodes {
+        node.orphan = !linked.contains(&node.id);
+    }
+
+    Graph {
+        nodes,
+        edges: edges.into_iter().collect(),
+    }
+}
+
+/// Keep only the notes within `depth` links of `root`, following links in either direction
+fn neighbourhood(graph: Graph, root: &str, depth: Option<usize>) -> Graph {
+    let mut neighbours: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+
+    for edge in &graph.edges {
+        neighbours
+            .entry(&edge.source)
+            .or_default()
+            .push(&edge.target);
+        neighbours
+            .entry(&edge.target)
+            .or_default()
+            .push(&edge.source);
+    }
+
+    let mut distances = BTreeMap::from([(root, 0)]);
+    let mut queue = VecDeque::from([root]);
+
+    while let Some(note) = queue.pop_front() {
+        let distance = distances[note];
+
+        if depth.is_some_and(|depth| distance >= depth) {
+            continue;
+        }
+
+        for &neighbour in neighbours.get(note).into_iter().flatten() {
+            if !distances.contains_key(neighbour) {
+                distances.insert(neighbour, distance + 1);
+                queue.push_back(neighbour);
+            }
+        }
+    }
+
+    let kept = distances
+        .into_keys()
+        .map(str::to_string)
+        .collect::<BTreeSet<String>>();
+
+    Graph {
+        nodes: graph
+            .nodes
+            .into_iter()
+            .filter(|node| kept.contains(&node.id))
+            .collect(),
+        edges: graph
+            .edges
+            .into_iter()
+            .filter(|edge| kept.contains(&edge.source) && kept.contains(&edge.target))
+            .collect(),
+    }
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn render_dot(graph: &Graph) -> String {
+    let mut dot = String::from("digraph notes {\n\tnode [shape=box];\n");
+
+    for node in &graph.nodes {
+        let mut attributes = format!("label=\"{}\"", escape_dot(&node.title));
+
+        if !node.tags.is_empty() {
+            let _ = write!(
+                attributes,
+                ", tooltip=\"{}\"",
+                escape_dot(&node.tags.join(", "))
+            );
+        }
+
+        // Orphans stand out so they can be linked up or removed
+        if node.orphan {
+            attributes.push_str(", style=dashed");
+        }
+
+        let _ = writeln!(dot, "\t\"{}\" [{attributes}];", escape_dot(&node.id));
+    }
+
+    for edge in &graph.edges {
+        let _ = writeln!(
+            dot,
+            "\t\"{}\" -> \"{}\";",
+            escape_dot(&edge.source),
+            escape_dot(&edge.target)
+        );
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+pub fn note_graph(format: Format, root: Option<&str>, depth: Option<usize>) {
+    let dir_path = notes_dir();
+
+    let mut graph = build_graph(&dir_path);
+
+    if let Some(root) = root {
+        graph = neighbourhood(graph, &find_note(&dir_path, root), depth);
+    }
+
+    match format {
+        Format::Dot => print!("{}", render_dot(&graph)),
+        Format::Json => match serde_json::to_string_pretty(&graph) {
+            Ok(json) => println!("{json}"),
+            Err(e) => error!("Could not serialise note graph: {e}"),
+        },
+    }
+}
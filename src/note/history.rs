@@ -0,0 +1,382 @@
+use std::collections::HashSet;
+use std::fs;
+
+use chrono::Local;
+use serde_derive::{Deserialize, Serialize};
+
+use super::{find_note, find_note_exact, meta, notes_dir, write_note};
+use crate::temp_control::cache_dir;
+use crate::{config, error, file, get_files, verify_filename};
+
+const HISTORY_DIR: &str = "history";
+const OBJECTS_DIR: &str = "objects";
+const INDEX_DIR: &str = "index";
+
+/// Snapshots kept for each note when no limit is configured
+const DEFAULT_HISTORY_LIMIT: usize = 50;
+/// Lines of unchanged text shown around each change in a diff
+const DIFF_CONTEXT: usize = 3;
+
+/// The snapshots of a single note
+#[derive(Serialize, Deserialize)]
+struct History {
+    /// Full path of the note
+    path: String,
+    #[serde(default)]
+    snapshots: Vec<Snapshot>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Snapshot {
+    /// Hash of the contents, which are stored in the objects directory
+    hash: String,
+    time: String,
+    size: u64,
+}
+
+fn history_dir() -> String {
+    format!("{}/{HISTORY_DIR}", cache_dir())
+}
+
+fn object_path(hash: &str) -> String {
+    format!("{}/{OBJECTS_DIR}/{hash}", history_dir())
+}
+
+fn index_path(path: &str) -> String {
+    format!(
+        "{}/{INDEX_DIR}/{}.toml",
+        history_dir(),
+        file::content_hash(path.as_bytes())
+    )
+}
+
+/// Maximum number of snapshots to keep for each note, `0` disables snapshots
+fn history_limit() -> usize {
+    config::get()
+        .ok()
+        .and_then(|config| {
+            config
+                .note
+                .as_ref()
+                .and_then(config::Note::settings)
+                .and_then(|settings| settings.history_limit)
+        })
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+}
+
+fn read_history(path: &str) -> History {
+    let index_path = index_path(path);
+
+    fs::read_to_string(&index_path).map_or_else(
+        |_| History {
+            path: path.to_string(),
+            snapshots: Vec::new(),
+        },
+        |contents| {
+            toml::from_str(&contents)
+                .unwrap_or_else(|e| error!("Could not read note history '{index_path}': {e}"))
+        },
+    )
+}
+
+fn write_history(history: &History) {
+    let index_path = index_path(&history.path);
+
+    let contents = toml::to_string(history)
+        .unwrap_or_else(|e| error!("Could not serialise note history: {e}"));
+
+    file::create_parent_dirs(&index_path);
+
+    if let Err(e) = fs::write(&index_path, contents) {
+        error!("Could not write note history '{index_path}': {e}");
+    }
+}
+
+fn push_snapshot(history: &mut History, contents: &str) {
+    let hash = file::content_hash(contents.as_bytes());
+
+    if history.snapshots.last().map(|s| &s.hash) == Some(&hash) {
+        return;
+    }
+
+    let object = object_path(&hash);
+
+    if verify_filename(&object).is_none() {
+        file::create_parent_dirs(&object);
+
+        if let Err(e) = fs::write(&object, contents) {
+            error!("Could not write snapshot '{object}': {e}");
+        }
+    }
+
+    history.snapshots.push(Snapshot {
+        hash,
+        time: Local::now().format(meta::DATE_FORMAT).to_string(),
+        size: contents.len() as u64,
+    });
+}
+
+/// Remove stored contents which no snapshot refers to any more
+fn remove_unused_objects() {
+    let index_dir = format!("{}/{INDEX_DIR}", history_dir());
+    let objects_dir = format!("{}/{OBJECTS_DIR}", history_dir());
+
+    if verify_filename(&index_dir).is_none() || verify_filename(&objects_dir).is_none() {
+        return;
+    }
+
+    let used = get_files(&index_dir)
+        .into_iter()
+        .filter_map(|index| fs::read_to_string(format!("{index_dir}/{index}")).ok())
+        .filter_map(|contents| toml::from_str::<History>(&contents).ok())
+        .flat_map(|history| history.snapshots.into_iter().map(|s| s.hash))
+        .collect::<HashSet<String>>();
+
+    for object in get_files(&objects_dir) {
+        if !used.contains(&object) {
+            let _ = fs::remove_file(format!("{objects_dir}/{object}"));
+        }
+    }
+}
+
+/// Record snapshots of a note which was changed from `before` to `after`
+pub fn record(path: &str, before: Option<&str>, after: &str) {
+    let limit = history_limit();
+    if limit == 0 {
+        return;
+    }
+
+    let mut history = read_history(path);
+
+    // Keep the contents from before the first recorded change so it can be reverted to
+    if let Some(before) = before {
+        push_snapshot(&mut history, before);
+    }
+    push_snapshot(&mut history, after);
+
+    let excess = history.snapshots.len().saturating_sub(limit);
+    history.snapshots.drain(..excess);
+
+    write_history(&history);
+
+    if excess > 0 {
+        remove_unused_objects();
+    }
+}
+
+/// Remove every snapshot of a note, so none of its earlier contents can be read back
+pub fn forget(path: &str) {
+    let index_path = index_path(path);
+
+    if verify_filename(&index_path).is_none() {
+        return;
+    }
+
+    if let Err(e) = fs::remove_file(&index_path) {
+        error!("Could not remove note history '{index_path}': {e}");
+    }
+
+    remove_unused_objects();
+}
+
+fn read_snapshot(snapshot: &Snapshot) -> String {
+    let object = object_path(&snapshot.hash);
+
+    fs::read_to_string(&object)
+        .unwrap_or_else(|e| error!("Could not read snapshot '{object}': {e}"))
+}
+
+/// Find a note and the snapshot numbered `number` (starting at 1)
+fn find_snapshot(name: &str, number: usize) -> (String, Snapshot) {
+    let dir_path = notes_dir();
+    let note = find_note(&dir_path, name);
+
+    note_snapshot(&dir_path, &note, number)
+}
+
+/// Get the snapshot numbered `number` of a note which has already been found
+fn note_snapshot(dir_path: &str, note: &str, number: usize) -> (String, Snapshot) {
+    let path = format!("{dir_path}/{note}");
+
+    let history = read_history(&path);
+
+    match number.checked_sub(1).and_then(|i| history.snapshots.get(i)) {
+        Some(snapshot) => (path, snapshot.clone()),
+        None => error!(
+            "'{note}' has no snapshot {number} ({} snapshots recorded)",
+            history.snapshots.len()
+        ),
+    }
+}
+
+/// Split `name@n` into the note name and snapshot number
+#[must_use]
+pub fn parse_snapshot_name(name: &str) -> Option<(&str, usize)> {
+    let (note, number) = name.rsplit_once('@')?;
+
+    Some((note, number.parse().ok()?))
+}
+
+pub fn note_history(name: &str) {
+    let dir_path = notes_dir();
+    let note = find_note(&dir_path, name);
+
+    let history = read_history(&format!("{dir_path}/{note}"));
+
+    if history.snapshots.is_empty() {
+        println!("No snapshots of '{note}' have been recorded");
+        return;
+    }
+
+    println!("{note}");
+
+    let mut previous_size: Option<u64> = None;
+
+    for (index, snapshot) in history.snapshots.iter().enumerate() {
+        let delta = previous_size.map_or_else(String::new, |previous| {
+            if snapshot.size >= previous {
+                format!("+{}", snapshot.size - previous)
+            } else {
+                format!("-{}", previous - snapshot.size)
+            }
+        });
+
+        println!(
+            "\t{:>4}  {}  {:>8} bytes  {delta:>7}",
+            index + 1,
+            snapshot.time,
+            snapshot.size
+        );
+
+        previous_size = Some(snapshot.size);
+    }
+}
+
+/// Get the contents of a note's snapshot numbered `number`
+#[must_use]
+pub fn snapshot_contents(name: &str, number: usize) -> String {
+    read_snapshot(&find_snapshot(name, number).1)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Find the changes between two texts using their longest common subsequence of lines
+fn line_diff<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old = old.lines().collect::<Vec<&str>>();
+    let new = new.lines().collect::<Vec<&str>>();
+
+    // Length of the longest common subsequence of old[i..] and new[j..]
+    let mut lengths = vec![vec![0_usize; new.len() + 1]; old.len() + 1];
+
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut diff = Vec::new();
+
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            diff.push(DiffLine::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            diff.push(DiffLine::Removed(old[i]));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Added(new[j]));
+            j += 1;
+        }
+    }
+
+    diff.extend(old[i..].iter().map(|line| DiffLine::Removed(line)));
+    diff.extend(new[j..].iter().map(|line| DiffLine::Added(line)));
+
+    diff
+}
+
+fn print_diff(old: &str, new: &str) {
+    let diff = line_diff(old, new);
+
+    let changed = diff
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, DiffLine::Same(_)))
+        .map(|(index, _)| index)
+        .collect::<Vec<usize>>();
+
+    if changed.is_empty() {
+        println!("No differences");
+        return;
+    }
+
+    let mut printed_up_to: Option<usize> = None;
+
+    for &index in &changed {
+        let start = index.saturating_sub(DIFF_CONTEXT);
+        let end = (index + DIFF_CONTEXT).min(diff.len() - 1);
+
+        let first_unprinted = match printed_up_to {
+            Some(printed) if printed >= end => continue,
+            Some(printed) if printed + 1 >= start => printed + 1,
+            Some(_) => {
+                println!("...");
+                start
+            }
+            None => start,
+        };
+
+        for line in &diff[first_unprinted..=end] {
+            match line {
+                DiffLine::Same(text) => println!(" {text}"),
+                DiffLine::Removed(text) => println!("-{text}"),
+                DiffLine::Added(text) => println!("+{text}"),
+            }
+        }
+
+        printed_up_to = Some(end);
+    }
+}
+
+/// Show the changes from snapshot `from` to snapshot `to`, or to the current note
+pub fn note_history_diff(name: &str, from: usize, to: Option<usize>) {
+    let (path, from_snapshot) = find_snapshot(name, from);
+    let old = read_snapshot(&from_snapshot);
+
+    let new = to.map_or_else(
+        || {
+            fs::read_to_string(&path)
+                .unwrap_or_else(|e| error!("Could not read note '{path}': {e}"))
+        },
+        |to| read_snapshot(&find_snapshot(name, to).1),
+    );
+
+    print_diff(&old, &new);
+}
+
+pub fn note_revert(name: &str, number: usize) {
+    let dir_path = notes_dir();
+    let note = find_note_exact(&dir_path, name);
+    let (path, snapshot) = note_snapshot(&dir_path, &note, number);
+
+    let current = fs::read_to_string(&path).ok();
+
+    // Recorded like any other edit, so the revert becomes the latest snapshot and can be undone
+    write_note(&path, current.as_deref(), read_snapshot(&snapshot));
+
+    println!(
+        "Reverted '{path}' to snapshot {number} from {}",
+        snapshot.time
+    );
+}
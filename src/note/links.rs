@@ -0,0 +1,487 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use regex::{Captures, Regex};
+
+use super::{assets, find_note, note_files, notes_dir, resolve_note};
+use crate::{error, get_all_files};
+
+/// A `[[wiki-link]]` found in a note
+pub struct Link {
+    /// Name of the linked note, without any alias or heading
+    pub target: String,
+    /// Line number (starting at 1) which the link is on
+    pub line: usize,
+    pub text: String,
+}
+
+/// Matches `[[wiki-links]]` and `![[embeds]]`, capturing the `!`, target, heading and alias
+#[must_use]
+pub fn wiki_link_regex() -> Regex {
+    match Regex::new(r"(!?)\[\[([^\[\]|#]+)(#[^\[\]|]*)?(?:\|([^\[\]]*))?\]\]") {
+        Ok(re) => re,
+        Err(e) => error!("Error with link regex: {e}"), // User should not receive this message
+    }
+}
+
+/// Find every wiki-link in the contents of a note
+#[must_use]
+pub fn wiki_links(contents: &str) -> Vec<Link> {
+    let regex = wiki_link_regex();
+
+    contents
+        .lines()
+        .enumerate()
+        .flat_map(|(index, line)| {
+            regex
+                .captures_iter(line)
+                .map(|captures| Link {
+                    target: captures[2].trim().to_string(),
+                    line: index + 1,
+                    text: line.to_string(),
+                })
+                .collect::<Vec<Link>>()
+        })
+        .collect()
+}
+
+/// Find the notes which a note links to, with wiki-links or relative markdown links
+#[must_use]
+pub fn linked_notes(dir_path: &str, file_name: &str, contents: &str) -> Vec<String> {
+    let mut linked = wiki_links(contents)
+        .into_iter()
+        .filter_map(|link| resolve_note(dir_path, &link.target))
+        .collect::<Vec<String>>();
+
+    for captures in markdown_link_regex().captures_iter(contents) {
+        let target = &captures[3];
+
+        if !is_relative_link(target) {
+            continue;
+        }
+
+        let path = target.split('#').next().unwrap_or(target);
+
+        if let Some(linked_note) = join_normalised(parent_dir(file_name), path) {
+            if Path::new(&format!("{dir_path}/{linked_note}")).is_file() {
+                linked.push(linked_note);
+            }
+        }
+    }
+
+    // Attachments aren't notes, so links to them aren't counted
+    linked.retain(|note| !assets::is_asset(note));
+    linked.sort();
+    linked.dedup();
+    linked
+}
+
+/// Read the links of every note, keyed by the note's path relative to the notes directory
+#[must_use]
+pub fn all_links(dir_path: &str) -> Vec<(String, Vec<Link>)> {
+    note_files(dir_path)
+        .into_iter()
+        .filter_map(|file_name| {
+            let contents = fs::read_to_string(format!("{dir_path}/{file_name}")).ok()?;
+
+            Some((file_name, wiki_links(&contents)))
+        })
+        .collect()
+}
+
+pub fn note_links(name: &str) {
+    let dir_path = notes_dir();
+    let file_name = find_note(&dir_path, name);
+
+    let contents = fs::read_to_string(format!("{dir_path}/{file_name}"))
+        .unwrap_or_else(|e| error!("Could not read note '{file_name}': {e}"));
+
+    let links = wiki_links(&contents);
+
+    if links.is_empty() {
+        println!("'{file_name}' has no links");
+        return;
+    }
+
+    println!("{file_name}");
+
+    for link in links {
+        match resolve_note(&dir_path, &link.target) {
+            Some(resolved) => println!("\t{:>4}  {} -> {resolved}", link.line, link.target),
+            None => println!("\t{:>4}  {} (unresolved)", link.line, link.target),
+        }
+    }
+}
+
+pub fn note_backlinks(name: &str) {
+    let dir_path = notes_dir();
+    let file_name = find_note(&dir_path, name);
+
+    let mut found = false;
+
+    for (source, links) in all_links(&dir_path) {
+        for link in links {
+            if resolve_note(&dir_path, &link.target).as_ref() == Some(&file_name) {
+                println!("{source}:{}: {}", link.line, link.text.trim());
+                found = true;
+            }
+        }
+    }
+
+    if !found {
+        println!("No notes link to '{file_name}'");
+    }
+}
+
+/// A single link which was changed by a rewrite
+pub struct Change {
+    pub line: usize,
+    pub old: String,
+    pub new: String,
+}
+
+/// The new contents of a note after its links have been rewritten
+pub struct Rewrite {
+    /// Path of the note, relative to the notes directory, before any moves
+    pub file_name: String,
+    pub contents: String,
+    pub changes: Vec<Change>,
+}
+
+/// Matches markdown links and images, capturing the `!`, text and target
+#[must_use]
+pub fn markdown_link_regex() -> Regex {
+    match Regex::new(r"(!?)\[([^\[\]]*)\]\(([^()\s]+)\)") {
+        Ok(re) => re,
+        Err(e) => error!("Error with link regex: {e}"), // User should not receive this message
+    }
+}
+
+/// Whether a markdown link points to another file by a relative path
+fn is_relative_link(target: &str) -> bool {
+    !(target.contains("://") || target.starts_with(['#', '/']) || target.starts_with("mailto:"))
+}
+
+/// Whether a file is a markdown note, which is rendered rather than shown as plain text
+#[must_use]
+pub fn is_markdown(file_name: &str) -> bool {
+    matches!(
+        Path::new(file_name)
+            .extension()
+            .and_then(|ext| ext.to_str()),
+        Some("md" | "markdown")
+    )
+}
+
+#[must_use]
+pub fn parent_dir(file_name: &str) -> &str {
+    file_name.rsplit_once('/').map_or("", |(dir, _)| dir)
+}
+
+/// Join a relative path onto a folder, resolving any `.` and `..`
+///
+/// Returns `None` if the path leaves the notes directory
+#[must_use]
+pub fn join_normalised(dir: &str, path: &str) -> Option<String> {
+    let mut components: Vec<&str> = dir.split('/').filter(|c| !c.is_empty()).collect();
+
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop()?;
+            }
+            other => components.push(other),
+        }
+    }
+
+    Some(components.join("/"))
+}
+
+/// Get the path to `target` relative to the folder `from_dir`
+#[must_use]
+pub fn relative_path(from_dir: &str, target: &str) -> String {
+    let from = from_dir
+        .split('/')
+        .filter(|c| !c.is_empty())
+        .collect::<Vec<&str>>();
+    let to = target.split('/').collect::<Vec<&str>>();
+
+    let common = from
+        .iter()
+        .zip(to.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut parts = vec![".."; from.len() - common];
+    parts.extend(&to[common..]);
+
+    parts.join("/")
+}
+
+#[must_use]
+pub fn strip_extension(name: &str) -> &str {
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !ext.contains('/') && !stem.ends_with('/') && !stem.is_empty() => stem,
+        _ => name,
+    }
+}
+
+fn rewrite_wiki_target(
+    dir_path: &str,
+    target: &str,
+    moves: &BTreeMap<String, String>,
+) -> Option<String> {
+    let resolved = resolve_note(dir_path, target)?;
+    let new_name = moves.get(&resolved)?;
+
+    if Path::new(target).extension().is_some() {
+        Some(new_name.clone())
+    } else {
+        Some(strip_extension(new_name).to_string())
+    }
+}
+
+fn rewrite_markdown_target(
+    file_name: &str,
+    new_file_name: &str,
+    target: &str,
+    moves: &BTreeMap<String, String>,
+) -> Option<String> {
+    // Only relative links to other files are rewritten
+    if !is_relative_link(target) {
+        return None;
+    }
+
+    let (path, anchor) = match target.split_once('#') {
+        Some((path, anchor)) => (path, format!("#{anchor}")),
+        None => (target, String::new()),
+    };
+
+    let old_target = join_normalised(parent_dir(file_name), path)?;
+
+    if file_name == new_file_name && !moves.contains_key(&old_target) {
+        return None;
+    }
+
+    let new_target = moves.get(&old_target).unwrap_or(&old_target);
+
+    let rewritten = format!(
+        "{}{anchor}",
+        relative_path(parent_dir(new_file_name), new_target)
+    );
+
+    if rewritten == target {
+        None
+    } else {
+        Some(rewritten)
+    }
+}
+
+/// Work out how every note's links need to change when notes are moved
+///
+/// `moves` maps the old path of each moved note to its new path, both relative to the notes
+/// directory. This must be run before the notes are actually moved
+#[must_use]
+pub fn plan_rewrites(dir_path: &str, moves: &BTreeMap<String, String>) -> Vec<Rewrite> {
+    let wiki_regex = wiki_link_regex();
+    let markdown_regex = markdown_link_regex();
+
+    get_all_files(dir_path)
+        .into_iter()
+        .filter_map(|file_name| {
+            let contents = fs::read_to_string(format!("{dir_path}/{file_name}")).ok()?;
+            let new_file_name = moves.get(&file_name).unwrap_or(&file_name).clone();
+
+            let mut changes: Vec<Change> = Vec::new();
+
+            let lines = contents
+                .split_inclusive('\n')
+                .enumerate()
+                .map(|(index, line)| {
+                    let line = wiki_regex.replace_all(line, |captures: &Captures| {
+                        let target = captures[2].trim();
+
+                        let Some(new_target) = rewrite_wiki_target(dir_path, target, moves) else {
+                            return captures[0].to_string();
+                        };
+
+                        let new_link = captures[0].replacen(target, &new_target, 1);
+                        changes.push(Change {
+                            line: index + 1,
+                            old: captures[0].to_string(),
+                            new: new_link.clone(),
+                        });
+                        new_link
+                    });
+
+                    markdown_regex
+                        .replace_all(&line, |captures: &Captures| {
+                            let target = &captures[3];
+
+                            let Some(new_target) =
+                                rewrite_markdown_target(&file_name, &new_file_name, target, moves)
+                            else {
+                                return captures[0].to_string();
+                            };
+
+                            let new_link =
+                                format!("{}[{}]({new_target})", &captures[1], &captures[2]);
+                            changes.push(Change {
+                                line: index + 1,
+                                old: captures[0].to_string(),
+                                new: new_link.clone(),
+                            });
+                            new_link
+                        })
+                        .into_owned()
+                })
+                .collect::<String>();
+
+            if changes.is_empty() {
+                None
+            } else {
+                Some(Rewrite {
+                    file_name,
+                    contents: lines,
+                    changes,
+                })
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::fs;
+
+    use super::{join_normalised, plan_rewrites, relative_path};
+
+    /// Create a notes directory holding the given notes, removing any left from an earlier run
+    fn notes_dir(name: &str, notes: &[(&str, &str)]) -> String {
+        let dir_path = std::env::temp_dir()
+            .join(format!("ntsr-test-{name}-{}", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+
+        let _ = fs::remove_dir_all(&dir_path);
+
+        for (file_name, contents) in notes {
+            let path = format!("{dir_path}/{file_name}");
+            let parent = path.rsplit_once('/').map_or("", |(parent, _)| parent);
+
+            assert!(fs::create_dir_all(parent).is_ok());
+            assert!(fs::write(&path, contents).is_ok());
+        }
+
+        dir_path
+    }
+
+    #[test]
+    fn join_resolves_dots() {
+        assert_eq!(join_normalised("", "a.md"), Some("a.md".to_string()));
+        assert_eq!(
+            join_normalised("projects/2024", "./plan.md"),
+            Some("projects/2024/plan.md".to_string())
+        );
+        assert_eq!(
+            join_normalised("projects/2024", "../../index.md"),
+            Some("index.md".to_string())
+        );
+        assert_eq!(
+            join_normalised("projects", "../archive/./old/guide.md"),
+            Some("archive/old/guide.md".to_string())
+        );
+    }
+
+    #[test]
+    fn join_rejects_leaving_the_notes_directory() {
+        assert_eq!(join_normalised("", "../outside.md"), None);
+        assert_eq!(join_normalised("projects", "../../outside.md"), None);
+    }
+
+    #[test]
+    fn relative_between_folders() {
+        assert_eq!(relative_path("", "projects/plan.md"), "projects/plan.md");
+        assert_eq!(relative_path("projects", "projects/plan.md"), "plan.md");
+        assert_eq!(relative_path("projects", "index.md"), "../index.md");
+        assert_eq!(
+            relative_path("archive/old", "projects/plan.md"),
+            "../../projects/plan.md"
+        );
+        assert_eq!(
+            relative_path("projects/2024", "projects/notes/plan.md"),
+            "../notes/plan.md"
+        );
+    }
+
+    #[test]
+    fn relative_undoes_join() {
+        for (dir, target) in [
+            ("", "index.md"),
+            ("projects", "archive/old/guide.md"),
+            ("a/b/c", "a/d/e.md"),
+        ] {
+            assert_eq!(
+                join_normalised(dir, &relative_path(dir, target)),
+                Some(target.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn rewrites_links_to_and_from_a_moved_note() {
+        let dir_path = notes_dir(
+            "rewrites",
+            &[
+                (
+                    "index.md",
+                    "[Guide](projects/guide.md)\nSee [[projects/guide]]\n",
+                ),
+                (
+                    "projects/guide.md",
+                    "[Home](../index.md) and [Plan](plan.md#goals)\n[Site](https://example.com)\n![Map](../assets/map.png)\n",
+                ),
+                ("projects/plan.md", "Back to [the guide](./guide.md)\n"),
+                ("other.md", "[Plan](projects/plan.md)\n"),
+            ],
+        );
+
+        let moves = BTreeMap::from([(
+            "projects/guide.md".to_string(),
+            "archive/old/guide.md".to_string(),
+        )]);
+
+        let mut rewrites = plan_rewrites(&dir_path, &moves);
+        rewrites.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+
+        let _ = fs::remove_dir_all(&dir_path);
+
+        let files = rewrites
+            .iter()
+            .map(|rewrite| rewrite.file_name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(files, ["index.md", "projects/guide.md", "projects/plan.md"]);
+
+        assert_eq!(
+            rewrites[0].contents,
+            "[Guide](archive/old/guide.md)\nSee [[archive/old/guide]]\n"
+        );
+        assert_eq!(rewrites[0].changes.len(), 2);
+        assert_eq!(rewrites[0].changes[1].line, 2);
+
+        // Links out of the moved note follow it, other links are left alone
+        assert_eq!(
+            rewrites[1].contents,
+            "[Home](../../index.md) and [Plan](../../projects/plan.md#goals)\n[Site](https://example.com)\n![Map](../../assets/map.png)\n"
+        );
+
+        assert_eq!(
+            rewrites[2].contents,
+            "Back to [the guide](../archive/old/guide.md)\n"
+        );
+        assert_eq!(rewrites[2].changes[0].old, "[the guide](./guide.md)");
+    }
+}
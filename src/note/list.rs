@@ -0,0 +1,131 @@
+use std::cmp::Reverse;
+use std::fs;
+use std::time::SystemTime;
+
+use chrono::{DateTime, Local, NaiveDateTime};
+use clap::ValueEnum;
+
+use super::{crypt, meta, note_files, notes_dir};
+
+const LIST_DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortKey {
+    Name,
+    Mtime,
+    Ctime,
+    Size,
+    Words,
+}
+
+/// Details of a note shown in a long listing
+struct NoteInfo {
+    name: String,
+    size: u64,
+    /// Unknown for encrypted notes
+    words: Option<usize>,
+    modified: NaiveDateTime,
+    created: NaiveDateTime,
+}
+
+fn local_time(time: SystemTime) -> NaiveDateTime {
+    DateTime::<Local>::from(time).naive_local()
+}
+
+fn note_info(dir_path: &str, name: String) -> Option<NoteInfo> {
+    let path = format!("{dir_path}/{name}");
+    let metadata = fs::metadata(&path).ok()?;
+
+    let modified = metadata.modified().map_or(NaiveDateTime::MIN, local_time);
+
+    let contents = if crypt::is_encrypted(&name) {
+        None
+    } else {
+        fs::read_to_string(&path).ok()
+    };
+
+    // The creation time in a note's metadata survives copies and checkouts, unlike the file's
+    let created = contents
+        .as_deref()
+        .and_then(meta::parse)
+        .and_then(|front_matter| front_matter.created)
+        .and_then(|created| NaiveDateTime::parse_from_str(&created, meta::DATE_FORMAT).ok())
+        .or_else(|| metadata.created().ok().map(local_time))
+        .unwrap_or(modified);
+
+    let words = contents
+        .as_deref()
+        .map(|contents| meta::split(contents).1.split_whitespace().count());
+
+    Some(NoteInfo {
+        name,
+        size: metadata.len(),
+        words,
+        modified,
+        created,
+    })
+}
+
+fn sort_notes(notes: &mut [NoteInfo], key: SortKey) {
+    // Everything but names is sorted newest or largest first
+    match key {
+        SortKey::Name => notes.sort_by(|a, b| a.name.cmp(&b.name)),
+        SortKey::Mtime => notes.sort_by_key(|note| Reverse(note.modified)),
+        SortKey::Ctime => notes.sort_by_key(|note| Reverse(note.created)),
+        SortKey::Size => notes.sort_by_key(|note| Reverse(note.size)),
+        SortKey::Words => notes.sort_by_key(|note| Reverse(note.words)),
+    }
+}
+
+/// List notes as a flat list, optionally with their details
+pub fn note_list_flat(
+    tag: Option<&str>,
+    long: bool,
+    sort: SortKey,
+    reverse: bool,
+    limit: Option<usize>,
+) {
+    let dir_path = notes_dir();
+
+    let mut notes = note_files(&dir_path)
+        .into_iter()
+        .filter(|name| {
+            tag.is_none_or(|tag| {
+                fs::read_to_string(format!("{dir_path}/{name}"))
+                    .ok()
+                    .and_then(|contents| meta::parse(&contents))
+                    .is_some_and(|front_matter| front_matter.tags.iter().any(|t| t == tag))
+            })
+        })
+        .filter_map(|name| note_info(&dir_path, name))
+        .collect::<Vec<NoteInfo>>();
+
+    sort_notes(&mut notes, sort);
+
+    if reverse {
+        notes.reverse();
+    }
+
+    if long && !notes.is_empty() {
+        println!(
+            "{:>8}  {:>6}  {:<16}  {:<16}  name",
+            "size", "words", "modified", "created"
+        );
+    }
+
+    for note in notes.iter().take(limit.unwrap_or(usize::MAX)) {
+        if long {
+            println!(
+                "{:>8}  {:>6}  {}  {}  {}",
+                note.size,
+                note.words
+                    .map_or_else(|| "-".to_string(), |words| words.to_string()),
+                note.modified.format(LIST_DATE_FORMAT),
+                note.created.format(LIST_DATE_FORMAT),
+                note.name
+            );
+        } else {
+            println!("{}", note.name);
+        }
+    }
+}
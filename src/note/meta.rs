@@ -0,0 +1,245 @@
+use std::time::SystemTime;
+
+use chrono::{DateTime, Local};
+use serde_derive::Serialize;
+
+const TOML_DELIMITER: &str = "+++";
+const YAML_DELIMITER: &str = "---";
+
+/// Format of the dates written to front matter
+pub const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Front-matter keys which are read into `FrontMatter`
+const KNOWN_KEYS: [&str; 4] = ["title", "created", "updated", "tags"];
+
+/// Metadata stored in a block at the top of a note
+#[derive(Debug, Default, Serialize)]
+pub struct FrontMatter {
+    pub title: Option<String>,
+    pub created: Option<String>,
+    pub updated: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl FrontMatter {
+    #[must_use]
+    pub fn new(title: &str, tags: &[String]) -> Self {
+        let now = now();
+
+        Self {
+            title: Some(title.to_string()),
+            created: Some(now.clone()),
+            updated: Some(now),
+            tags: tags.to_vec(),
+        }
+    }
+
+    /// Render the metadata as a TOML front-matter block
+    #[must_use]
+    pub fn render(&self) -> String {
+        let body = toml::to_string(self).unwrap_or_default();
+
+        format!("{TOML_DELIMITER}\n{body}{TOML_DELIMITER}\n")
+    }
+}
+
+#[must_use]
+pub fn now() -> String {
+    Local::now().format(DATE_FORMAT).to_string()
+}
+
+/// Format a file's timestamp the same way as the dates in front-matter
+#[must_use]
+pub fn format_time(time: SystemTime) -> String {
+    DateTime::<Local>::from(time)
+        .format(DATE_FORMAT)
+        .to_string()
+}
+
+/// Split a note into its front-matter block (without delimiters) and its body
+#[must_use]
+pub fn split(contents: &str) -> (Option<&str>, &str) {
+    let Some(delimiter) = [TOML_DELIMITER, YAML_DELIMITER]
+        .into_iter()
+        .find(|delimiter| contents.lines().next().map(str::trim_end) == Some(*delimiter))
+    else {
+        return (None, contents);
+    };
+
+    let block_start = contents.find('\n').map_or(contents.len(), |i| i + 1);
+    let mut offset = block_start;
+
+    for line in contents[block_start..].split_inclusive('\n') {
+        if line.trim_end() == delimiter {
+            return (
+                Some(&contents[block_start..offset]),
+                &contents[offset + line.len()..],
+            );
+        }
+
+        offset += line.len();
+    }
+
+    // Unterminated blocks are treated as part of the body
+    (None, contents)
+}
+
+/// Parse the front-matter of a note, if it has any
+#[must_use]
+pub fn parse(contents: &str) -> Option<FrontMatter> {
+    let (block, _) = split(contents);
+    let block = block?;
+
+    if contents.starts_with(TOML_DELIMITER) {
+        parse_toml(block)
+    } else {
+        Some(parse_yaml(block))
+    }
+}
+
+/// Keys of a note's front-matter which aren't read into `FrontMatter`
+#[must_use]
+pub fn other_keys(contents: &str) -> Vec<String> {
+    let Some(block) = split(contents).0 else {
+        return Vec::new();
+    };
+
+    let keys = if contents.starts_with(TOML_DELIMITER) {
+        block
+            .parse::<toml::Value>()
+            .ok()
+            .and_then(|value| {
+                value
+                    .as_table()
+                    .map(|table| table.keys().cloned().collect())
+            })
+            .unwrap_or_default()
+    } else {
+        // Nested values and list items are indented or start with a dash
+        block
+            .lines()
+            .filter(|line| !line.starts_with([' ', '\t', '-', '#']))
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, _)| key.trim().to_string())
+            .collect::<Vec<String>>()
+    };
+
+    keys.into_iter()
+        .filter(|key| !key.is_empty() && !KNOWN_KEYS.contains(&key.as_str()))
+        .collect()
+}
+
+fn parse_toml(block: &str) -> Option<FrontMatter> {
+    let table = block.parse::<toml::Value>().ok()?;
+
+    let field = |key: &str| {
+        table.get(key).map(|value| match value {
+            toml::Value::String(string) => string.clone(),
+            other => other.to_string(),
+        })
+    };
+
+    let tags = match table.get("tags") {
+        Some(toml::Value::Array(tags)) => tags
+            .iter()
+            .filter_map(|tag| tag.as_str().map(str::to_string))
+            .collect(),
+        Some(toml::Value::String(tag)) => vec![tag.clone()],
+        _ => Vec::new(),
+    };
+
+    Some(FrontMatter {
+        title: field("title"),
+        created: field("created"),
+        updated: field("updated"),
+        tags,
+    })
+}
+
+fn unquote(value: &str) -> String {
+    value
+        .trim()
+        .trim_matches(|c| c == '"' || c == '\'')
+        .to_string()
+}
+
+/// Parse the small subset of YAML which is used for note metadata
+fn parse_yaml(block: &str) -> FrontMatter {
+    let mut front_matter = FrontMatter::default();
+    let mut in_tags = false;
+
+    for line in block.lines() {
+        if in_tags {
+            if let Some(tag) = line.trim_start().strip_prefix("- ") {
+                front_matter.tags.push(unquote(tag));
+                continue;
+            }
+
+            in_tags = false;
+        }
+
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+
+        match key.trim() {
+            "title" => front_matter.title = Some(unquote(value)),
+            "created" => front_matter.created = Some(unquote(value)),
+            "updated" => front_matter.updated = Some(unquote(value)),
+            "tags" if value.is_empty() => in_tags = true,
+            "tags" => {
+                front_matter.tags = value
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .split(',')
+                    .map(unquote)
+                    .filter(|tag| !tag.is_empty())
+                    .collect();
+            }
+            _ => {}
+        }
+    }
+
+    front_matter
+}
+
+/// Refresh the `updated` field of a note's front-matter, returning the new contents
+#[must_use]
+pub fn touch_updated(contents: &str) -> Option<String> {
+    let (block, body) = split(contents);
+    let block = block?;
+
+    let delimiter = contents.lines().next()?.trim_end();
+    let updated_line = if delimiter == TOML_DELIMITER {
+        format!("updated = \"{}\"", now())
+    } else {
+        format!("updated: {}", now())
+    };
+
+    let mut found = false;
+    let mut lines = block
+        .lines()
+        .map(|line| {
+            let is_updated = line
+                .strip_prefix("updated")
+                .is_some_and(|rest| rest.trim_start().starts_with(['=', ':']));
+
+            if is_updated {
+                found = true;
+                updated_line.clone()
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<String>>();
+
+    if !found {
+        lines.push(updated_line);
+    }
+
+    Some(format!(
+        "{delimiter}\n{}\n{delimiter}\n{body}",
+        lines.join("\n")
+    ))
+}
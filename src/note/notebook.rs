@@ -0,0 +1,57 @@
+use std::path::Path;
+
+use super::{note_files, notes_dir};
+use crate::commands::Commands;
+use crate::{cli, config, error, verify_filename};
+
+/// Get the notebook given with `--notebook`, if any
+#[must_use]
+pub fn selected() -> Option<String> {
+    match cli().command {
+        Commands::Note { notebook, .. } => notebook,
+        _ => None,
+    }
+}
+
+/// Get the directory of the notebook which a note is in, so it's committed to the right repository
+#[must_use]
+pub fn dir_containing(path: &str) -> String {
+    config::get()
+        .ok()
+        .and_then(|config| {
+            config
+                .notebooks()
+                .into_values()
+                .filter(|dir| Path::new(path).starts_with(dir))
+                .max_by_key(|dir| dir.len())
+                .map(str::to_string)
+        })
+        .unwrap_or_else(notes_dir)
+}
+
+pub fn note_notebooks() {
+    let config = config::get().unwrap_or_else(|e| error!("{e}"));
+    let notebooks = config.notebooks();
+
+    if notebooks.is_empty() {
+        error!("No notebooks set, add a 'note' key or a [notebooks] table to the config");
+    }
+
+    let default = config.default_notebook();
+    let name_width = notebooks.keys().map(|name| name.len()).max().unwrap_or(0);
+
+    for (name, dir) in &notebooks {
+        let marker = if default == Some(*name) { '*' } else { ' ' };
+
+        let count = if verify_filename(dir).is_some() {
+            match note_files(dir).len() {
+                1 => "1 note".to_string(),
+                count => format!("{count} notes"),
+            }
+        } else {
+            "missing".to_string()
+        };
+
+        println!("{marker} {name:<name_width$}  {dir}  ({count})");
+    }
+}
@@ -0,0 +1,466 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+
+use chrono::{Days, Local, NaiveDate};
+use regex::Regex;
+use serde_derive::{Deserialize, Serialize};
+
+use super::{crypt, is_code_fence, meta, notes_dir, notes_in};
+use crate::temp_control::cache_dir;
+use crate::{error, file};
+
+const SCHEDULE_FILE: &str = "review.toml";
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Ease which cards start with, and the lowest it can fall to
+const INITIAL_EASE: f64 = 2.5;
+const MINIMUM_EASE: f64 = 1.3;
+/// Lowest grade which counts as remembering a card
+const PASSING_GRADE: u8 = 3;
+const MAXIMUM_GRADE: u8 = 5;
+
+/// A question and its answer, from a note
+struct Card {
+    note: String,
+    question: String,
+    answer: String,
+}
+
+impl Card {
+    /// Cards are tracked by their note and question, so answers can be corrected
+    fn id(&self, dir_path: &str) -> String {
+        let key = format!("{dir_path}/{}\n{}", self.note, self.question);
+
+        file::content_hash(key.as_bytes())[..32].to_string()
+    }
+}
+
+/// How well a card is known and when it should next be reviewed
+#[derive(Clone, Serialize, Deserialize)]
+struct CardState {
+    ease: f64,
+    /// Days until the next review
+    interval: u64,
+    /// Reviews in a row which passed
+    repetitions: u32,
+    due: String,
+}
+
+impl CardState {
+    const fn new() -> Self {
+        Self {
+            ease: INITIAL_EASE,
+            interval: 0,
+            repetitions: 0,
+            due: String::new(),
+        }
+    }
+
+    fn due_date(&self) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(&self.due, DATE_FORMAT).ok()
+    }
+
+    /// Schedule the next review following SM-2, from a grade between 0 and 5
+    // Intervals stay far below the point where floats lose precision
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    fn grade(&mut self, grade: u8, today: NaiveDate) {
+        if grade >= PASSING_GRADE {
+            self.interval = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (self.interval as f64 * self.ease).round() as u64,
+            };
+            self.repetitions += 1;
+        } else {
+            self.interval = 1;
+            self.repetitions = 0;
+        }
+
+        let distance = f64::from(MAXIMUM_GRADE - grade);
+        let penalty = distance * distance.mul_add(0.02, 0.08);
+        self.ease = (self.ease + 0.1 - penalty).max(MINIMUM_EASE);
+
+        self.due = today
+            .checked_add_days(Days::new(self.interval))
+            .unwrap_or(NaiveDate::MAX)
+            .format(DATE_FORMAT)
+            .to_string();
+    }
+}
+
+/// Scheduling state of every card which has been reviewed, by card id
+#[derive(Default, Serialize, Deserialize)]
+struct Schedule {
+    #[serde(default)]
+    cards: BTreeMap<String, CardState>,
+}
+
+fn schedule_path() -> String {
+    format!("{}/{SCHEDULE_FILE}", cache_dir())
+}
+
+fn read_schedule() -> Schedule {
+    let path = schedule_path();
+
+    fs::read_to_string(&path).map_or_else(
+        |_| Schedule::default(),
+        |contents| {
+            toml::from_str(&contents)
+                .unwrap_or_else(|e| error!("Could not read review schedule '{path}': {e}"))
+        },
+    )
+}
+
+/// Write the schedule to a temporary file first, so it's never left half written
+fn write_schedule(schedule: &Schedule) {
+    let path = schedule_path();
+    let temp_path = format!("{path}.tmp");
+
+    let contents = toml::to_string(schedule)
+        .unwrap_or_else(|e| error!("Could not serialise review schedule: {e}"));
+
+    if let Err(e) = fs::write(&temp_path, contents).and_then(|()| fs::rename(&temp_path, &path)) {
+        error!("Could not write review schedule '{path}': {e}");
+    }
+}
+
+/// Matches the list bullet at the start of a line
+fn bullet_regex() -> Regex {
+    match Regex::new(r"^\s*(?:[-*+]\s+)?") {
+        Ok(re) => re,
+        Err(e) => error!("Error with bullet regex: {e}"), // User should not receive this message
+    }
+}
+
+/// Find the `Q:`/`A:` pairs and `term :: definition` lines in a note, outside of code blocks
+///
+/// Answers carry on over the following lines until a blank line or the next question
+fn parse_cards(bullet: &Regex, note: &str, contents: &str) -> Vec<Card> {
+    let mut cards = Vec::new();
+    let mut question: Option<String> = None;
+    let mut answer: Option<String> = None;
+    let mut in_code_block = false;
+
+    let finish =
+        |cards: &mut Vec<Card>, question: &mut Option<String>, answer: &mut Option<String>| {
+            if let (Some(question), Some(answer)) = (question.take(), answer.take()) {
+                cards.push(Card {
+                    note: note.to_string(),
+                    question,
+                    answer,
+                });
+            }
+        };
+
+    for line in meta::split(contents).1.lines() {
+        if is_code_fence(line) {
+            in_code_block = !in_code_block;
+            finish(&mut cards, &mut question, &mut answer);
+            continue;
+        }
+
+        if in_code_block {
+            continue;
+        }
+
+        let text = bullet.replace(line, "");
+        let text = text.trim_end();
+
+        if let Some(q) = text.strip_prefix("Q:") {
+            finish(&mut cards, &mut question, &mut answer);
+            question = Some(q.trim().to_string());
+        } else if let Some(a) = text.strip_prefix("A:").filter(|_| question.is_some()) {
+            answer = Some(a.trim().to_string());
+        } else if text.is_empty() {
+            // Questions without an answer are dropped
+            finish(&mut cards, &mut question, &mut answer);
+        } else if let Some(answer) = answer.as_mut() {
+            answer.push('\n');
+            answer.push_str(text);
+        } else if let Some(question) = question.as_mut() {
+            question.push('\n');
+            question.push_str(text);
+        } else if let Some((term, definition)) = text.split_once(" :: ") {
+            if !term.trim().is_empty() && !definition.trim().is_empty() {
+                cards.push(Card {
+                    note: note.to_string(),
+                    question: term.trim().to_string(),
+                    answer: definition.trim().to_string(),
+                });
+            }
+        }
+    }
+
+    finish(&mut cards, &mut question, &mut answer);
+
+    cards
+}
+
+/// Every card in the notes, optionally only those within a folder of the notes directory
+fn find_cards(dir_path: &str, folder: Option<&str>) -> Vec<Card> {
+    let bullet = bullet_regex();
+
+    notes_in(dir_path, folder)
+        .into_iter()
+        .filter(|note| !crypt::is_encrypted(note))
+        .flat_map(|note| {
+            fs::read_to_string(format!("{dir_path}/{note}"))
+                .map(|contents| parse_cards(&bullet, &note, &contents))
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+fn read_input(prompt: &str) -> Option<String> {
+    print!("{prompt}");
+    let _ = std::io::stdout().flush();
+
+    let mut input = String::new();
+
+    match std::io::stdin().read_line(&mut input) {
+        // The end of input ends the session
+        Ok(0) => None,
+        Ok(_) => Some(input.trim().to_string()),
+        Err(e) => error!("Could not parse input: {e}"),
+    }
+}
+
+/// Ask for a grade until a valid one is given, or `None` if the session should stop
+fn read_grade() -> Option<u8> {
+    loop {
+        let input = read_input(&format!(
+            "Grade from 0 (forgot) to {MAXIMUM_GRADE} (easy), or q to quit: "
+        ))?;
+
+        if input == "q" {
+            return None;
+        }
+
+        match input.parse::<u8>() {
+            Ok(grade) if grade <= MAXIMUM_GRADE => return Some(grade),
+            _ => println!("'{input}' is not a grade"),
+        }
+    }
+}
+
+fn plural(count: u64, word: &str) -> String {
+    if count == 1 {
+        format!("{count} {word}")
+    } else {
+        format!("{count} {word}s")
+    }
+}
+
+/// Quiz the cards which are due, then any new ones, recording how well each was remembered
+pub fn note_review(folder: Option<&str>, limit: usize) {
+    let dir_path = notes_dir();
+    let today = Local::now().date_naive();
+
+    let mut schedule = read_schedule();
+
+    let mut due = Vec::new();
+    let mut new = Vec::new();
+
+    for card in find_cards(&dir_path, folder) {
+        let id = card.id(&dir_path);
+
+        match schedule.cards.get(&id).and_then(CardState::due_date) {
+            Some(date) if date <= today => due.push((date, id, card)),
+            Some(_) => {}
+            None => new.push((today, id, card)),
+        }
+    }
+
+    // Cards which have waited longest come first
+    due.sort_by_key(|(date, _, _)| *date);
+
+    let session = due
+        .into_iter()
+        .chain(new)
+        .take(limit)
+        .collect::<Vec<(NaiveDate, String, Card)>>();
+
+    if session.is_empty() {
+        println!("No cards are due for review");
+        return;
+    }
+
+    let total = session.len();
+    let mut reviewed = 0;
+
+    for (index, (_, id, card)) in session.into_iter().enumerate() {
+        println!("\n[{}/{total}] {}", index + 1, card.note);
+        println!("Q: {}", card.question);
+
+        if read_input("Press enter to show the answer ").is_none() {
+            break;
+        }
+
+        println!("A: {}", card.answer);
+
+        let Some(grade) = read_grade() else {
+            break;
+        };
+
+        let state = schedule.cards.entry(id).or_insert_with(CardState::new);
+        state.grade(grade, today);
+
+        println!("Next review in {}", plural(state.interval, "day"));
+
+        // Saved after every card, so quitting part way through keeps what was reviewed
+        write_schedule(&schedule);
+        reviewed += 1;
+    }
+
+    println!("\nReviewed {reviewed} of {total} cards");
+}
+
+/// Show how many cards there are in each note and when they're due
+pub fn review_stats(folder: Option<&str>) {
+    let dir_path = notes_dir();
+    let today = Local::now().date_naive();
+    let week = today
+        .checked_add_days(Days::new(7))
+        .unwrap_or(NaiveDate::MAX);
+
+    let schedule = read_schedule();
+    let cards = find_cards(&dir_path, folder);
+
+    if cards.is_empty() {
+        println!("No cards found, add Q:/A: pairs or 'term :: definition' lines to notes");
+        return;
+    }
+
+    // Counts of cards which are new, due now, due within a week and in total
+    let mut notes: BTreeMap<&str, [usize; 4]> = BTreeMap::new();
+
+    for card in &cards {
+        let counts = notes.entry(&card.note).or_default();
+
+        match schedule
+            .cards
+            .get(&card.id(&dir_path))
+            .and_then(CardState::due_date)
+        {
+            None => counts[0] += 1,
+            Some(date) if date <= today => counts[1] += 1,
+            Some(date) if date <= week => counts[2] += 1,
+            Some(_) => {}
+        }
+
+        counts[3] += 1;
+    }
+
+    let name_width = notes
+        .keys()
+        .map(|note| note.len())
+        .max()
+        .unwrap_or(0)
+        .max(4);
+
+    println!(
+        "{:<name_width$}  {:>5}  {:>5}  {:>9}  {:>5}",
+        "note", "new", "due", "this week", "total"
+    );
+
+    let mut totals = [0; 4];
+
+    for (note, counts) in &notes {
+        println!(
+            "{note:<name_width$}  {:>5}  {:>5}  {:>9}  {:>5}",
+            counts[0], counts[1], counts[2], counts[3]
+        );
+
+        for (total, count) in totals.iter_mut().zip(counts) {
+            *total += count;
+        }
+    }
+
+    println!(
+        "{:<name_width$}  {:>5}  {:>5}  {:>9}  {:>5}",
+        "total", totals[0], totals[1], totals[2], totals[3]
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::{CardState, INITIAL_EASE, MINIMUM_EASE};
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, day).unwrap_or(NaiveDate::MIN)
+    }
+
+    #[test]
+    fn intervals_grow_with_each_pass() {
+        let mut state = CardState::new();
+
+        state.grade(4, day(1));
+        assert_eq!((state.interval, state.repetitions), (1, 1));
+        assert_eq!(state.due_date(), Some(day(2)));
+
+        state.grade(4, day(2));
+        assert_eq!((state.interval, state.repetitions), (6, 2));
+        assert_eq!(state.due_date(), Some(day(8)));
+
+        // A grade of 4 leaves the ease where it started
+        assert!((state.ease - INITIAL_EASE).abs() < 1e-9);
+
+        state.grade(4, day(8));
+        assert_eq!(state.interval, 15);
+        assert_eq!(state.due_date(), Some(day(23)));
+    }
+
+    #[test]
+    fn ease_follows_the_grade() {
+        let mut easy = CardState::new();
+        easy.grade(5, day(1));
+        assert!((easy.ease - 2.6).abs() < 1e-9);
+
+        let mut hard = CardState::new();
+        hard.grade(3, day(1));
+        assert!((hard.ease - 2.36).abs() < 1e-9);
+        assert_eq!(hard.interval, 1);
+    }
+
+    #[test]
+    fn failing_starts_the_card_over() {
+        let mut state = CardState::new();
+
+        for today in [1, 2, 8] {
+            state.grade(5, day(today));
+        }
+        assert_eq!(state.repetitions, 3);
+
+        state.grade(2, day(24));
+        assert_eq!((state.interval, state.repetitions), (1, 0));
+        assert_eq!(state.due_date(), Some(day(25)));
+
+        state.grade(4, day(25));
+        assert_eq!(state.interval, 1);
+    }
+
+    #[test]
+    fn ease_never_falls_below_the_minimum() {
+        let mut state = CardState::new();
+
+        for today in 1..=10 {
+            state.grade(0, day(today));
+        }
+
+        assert!((state.ease - MINIMUM_EASE).abs() < 1e-9);
+
+        // Intervals keep growing from the lowest ease once the card is passed again
+        for today in [11, 12, 18] {
+            state.grade(3, day(today));
+        }
+
+        assert_eq!(state.interval, 8);
+    }
+}
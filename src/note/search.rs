@@ -0,0 +1,127 @@
+use std::fs;
+
+use regex::{Regex, RegexBuilder};
+
+use super::{note_files, notes_dir};
+use crate::error;
+
+/// All the matches found within a single note
+struct FileMatches {
+    file_name: String,
+    lines: Vec<String>,
+    hits: Vec<usize>,
+}
+
+fn build_regex(pattern: &str, fixed: bool, ignore_case: bool) -> Regex {
+    let pattern = if fixed {
+        regex::escape(pattern)
+    } else {
+        pattern.to_string()
+    };
+
+    RegexBuilder::new(&pattern)
+        .case_insensitive(ignore_case)
+        .build()
+        .unwrap_or_else(|e| error!("Invalid search pattern: {e}"))
+}
+
+fn print_matches(matches: &FileMatches, context: usize) {
+    let last_line = matches.lines.len().saturating_sub(1);
+    let mut printed_up_to: Option<usize> = None;
+
+    for &hit in &matches.hits {
+        let start = hit.saturating_sub(context);
+        let end = (hit + context).min(last_line);
+
+        // Separate groups of lines which aren't contiguous
+        let first_unprinted = match printed_up_to {
+            Some(printed) if printed >= end => continue,
+            Some(printed) if printed + 1 >= start => printed + 1,
+            Some(_) => {
+                println!("--");
+                start
+            }
+            None => start,
+        };
+
+        for line_index in first_unprinted..=end {
+            let separator = if matches.hits.binary_search(&line_index).is_ok() {
+                ':'
+            } else {
+                '-'
+            };
+
+            println!(
+                "{}{separator}{}{separator}{}",
+                matches.file_name,
+                line_index + 1,
+                matches.lines[line_index]
+            );
+        }
+
+        printed_up_to = Some(end);
+    }
+}
+
+pub fn note_search(pattern: &str, fixed: bool, ignore_case: bool, context: usize) {
+    let dir_path = notes_dir();
+    let regex = build_regex(pattern, fixed, ignore_case);
+
+    let mut results = note_files(&dir_path)
+        .into_iter()
+        .filter_map(|file_name| {
+            // Skip anything which isn't text
+            let contents = fs::read_to_string(format!("{dir_path}/{file_name}")).ok()?;
+
+            let lines = contents
+                .lines()
+                .map(str::to_string)
+                .collect::<Vec<String>>();
+            let hits = lines
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| regex.is_match(line))
+                .map(|(index, _)| index)
+                .collect::<Vec<usize>>();
+
+            if hits.is_empty() {
+                None
+            } else {
+                Some(FileMatches {
+                    file_name,
+                    lines,
+                    hits,
+                })
+            }
+        })
+        .collect::<Vec<FileMatches>>();
+
+    if results.is_empty() {
+        println!("No matches found for '{pattern}'");
+        return;
+    }
+
+    // Notes with the most hits come first
+    results.sort_by(|a, b| {
+        b.hits
+            .len()
+            .cmp(&a.hits.len())
+            .then_with(|| a.file_name.cmp(&b.file_name))
+    });
+
+    for (index, matches) in results.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+
+        print_matches(matches, context);
+    }
+
+    let total_hits = results.iter().map(|m| m.hits.len()).sum::<usize>();
+
+    println!("\n{total_hits} matching lines in {} notes:", results.len());
+
+    for matches in &results {
+        println!("\t{:>4}  {}", matches.hits.len(), matches.file_name);
+    }
+}
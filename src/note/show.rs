@@ -0,0 +1,137 @@
+use std::fs;
+
+use super::{crypt, find_note, history, is_code_fence, meta, notes_dir};
+use crate::commands::run_pager;
+use crate::error;
+
+/// Inclusive range of line numbers, starting at 1
+#[derive(Clone, Copy)]
+pub struct LineRange {
+    start: usize,
+    end: Option<usize>,
+}
+
+/// Parse a line range in the form `A:B`, `A:`, `:B` or `A`
+///
+/// # Errors
+/// Returns an error if either bound is not a positive number, or if the range is backwards
+pub fn parse_lines(s: &str) -> Result<LineRange, String> {
+    let parse_bound = |bound: &str| -> Result<Option<usize>, String> {
+        if bound.is_empty() {
+            return Ok(None);
+        }
+
+        match bound.parse::<usize>() {
+            Ok(0) | Err(_) => Err(format!("'{bound}' is not a line number")),
+            Ok(line) => Ok(Some(line)),
+        }
+    };
+
+    let (start, end) = if let Some((start, end)) = s.split_once(':') {
+        (parse_bound(start)?, parse_bound(end)?)
+    } else {
+        let line = parse_bound(s)?;
+        (line, line)
+    };
+
+    let start = start.unwrap_or(1);
+
+    if end.is_some_and(|end| end < start) {
+        return Err(format!("Line range '{s}' ends before it starts"));
+    }
+
+    Ok(LineRange { start, end })
+}
+
+/// Join lines back into text ending with a newline
+fn join_lines(lines: &[&str]) -> String {
+    lines.iter().fold(String::new(), |mut text, line| {
+        text.push_str(line);
+        text.push('\n');
+        text
+    })
+}
+
+fn select_lines(contents: &str, range: LineRange) -> String {
+    let lines = contents
+        .lines()
+        .enumerate()
+        .skip(range.start - 1)
+        .take_while(|(index, _)| range.end.is_none_or(|end| *index < end))
+        .map(|(_, line)| line)
+        .collect::<Vec<&str>>();
+
+    join_lines(&lines)
+}
+
+/// Level and text of a markdown heading line
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let text = &line[level..];
+
+    ((1..=6).contains(&level) && (text.is_empty() || text.starts_with(' ')))
+        .then(|| (level, text.trim().trim_end_matches('#').trim_end()))
+}
+
+/// Get a markdown section, from its heading up to the next heading of the same or higher level
+fn select_section<'a>(body: &'a str, name: &str) -> Option<Vec<&'a str>> {
+    let mut section: Option<(usize, Vec<&str>)> = None;
+    let mut in_code_block = false;
+
+    for line in body.lines() {
+        if is_code_fence(line) {
+            in_code_block = !in_code_block;
+        }
+
+        let line_heading = (!in_code_block).then(|| heading(line)).flatten();
+
+        match (&mut section, line_heading) {
+            (Some((level, _)), Some((line_level, _))) if line_level <= *level => break,
+            (Some((_, lines)), _) => lines.push(line),
+            (None, Some((level, text))) if text.eq_ignore_ascii_case(name.trim()) => {
+                section = Some((level, vec![line]));
+            }
+            (None, _) => {}
+        }
+    }
+
+    section.map(|(_, lines)| lines)
+}
+
+pub fn note_show(name: &str, raw: bool, lines: Option<LineRange>, section: Option<&str>) {
+    let contents = if let Some((note, number)) = history::parse_snapshot_name(name) {
+        history::snapshot_contents(note, number)
+    } else {
+        let dir_path = notes_dir();
+        let path = format!("{dir_path}/{}", find_note(&dir_path, name));
+
+        if crypt::is_encrypted(&path) {
+            crypt::read_encrypted(&path).to_string()
+        } else {
+            fs::read_to_string(&path)
+                .unwrap_or_else(|e| error!("Could not read note '{path}': {e}"))
+        }
+    };
+
+    // Line numbers count from the top of the file, so they agree with `note search`
+    let output = if let Some(section) = section {
+        let (_, body) = meta::split(&contents);
+
+        select_section(body, section).map_or_else(
+            || error!("No section '{section}' found in '{name}'"),
+            |lines| join_lines(&lines),
+        )
+    } else if let Some(range) = lines {
+        select_lines(&contents, range)
+    } else if raw {
+        contents
+    } else {
+        meta::split(&contents).1.to_string()
+    };
+
+    if raw {
+        print!("{output}");
+    } else {
+        run_pager(&output);
+    }
+}
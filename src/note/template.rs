@@ -0,0 +1,109 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::Local;
+use regex::{Captures, Regex};
+
+use crate::{config, error, get_files, get_home, verify_filename};
+
+const DEFAULT_TEMPLATES_DIR: &str = ".config/noteiser/templates";
+
+/// Parse a `key=value` template variable from the command line
+///
+/// # Errors
+/// Returns an error if there is no `=` in the argument
+pub fn parse_var(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((key, value)) => Ok((key.trim().to_string(), value.to_string())),
+        None => Err(format!("Invalid variable '{arg}', expected KEY=VALUE")),
+    }
+}
+
+#[must_use]
+pub fn templates_dir() -> String {
+    config::get()
+        .ok()
+        .and_then(|config| config.templates)
+        .unwrap_or_else(|| format!("{}/{DEFAULT_TEMPLATES_DIR}", get_home()))
+}
+
+fn available_templates(directory: &str) -> Vec<String> {
+    if verify_filename(directory).is_none() {
+        return Vec::new();
+    }
+
+    let mut templates = get_files(&directory.to_string())
+        .into_iter()
+        .filter(|file| {
+            !file.starts_with('.') && Path::new(&format!("{directory}/{file}")).is_file()
+        })
+        .collect::<Vec<String>>();
+    templates.sort();
+
+    templates
+}
+
+/// Find the file of a template, the extension of the template can be left out
+#[must_use]
+pub fn find(name: &str) -> Option<String> {
+    let directory = templates_dir();
+
+    available_templates(&directory)
+        .into_iter()
+        .find(|file| {
+            file == name || Path::new(file).file_stem().and_then(|stem| stem.to_str()) == Some(name)
+        })
+        .map(|file| format!("{directory}/{file}"))
+}
+
+/// Fill in the `{{variables}}` of a template
+#[must_use]
+pub fn render(template_path: &str, title: &str, vars: &[(String, String)]) -> String {
+    let template = fs::read_to_string(template_path)
+        .unwrap_or_else(|e| error!("Could not read template '{template_path}': {e}"));
+
+    let now = Local::now();
+    let user = std::env::var("USER").unwrap_or_default();
+
+    let variable_regex = match Regex::new(r"\{\{\s*([A-Za-z0-9_-]+)\s*\}\}") {
+        Ok(re) => re,
+        Err(e) => error!("Error with template regex: {e}"), // User should not receive this message
+    };
+
+    variable_regex
+        .replace_all(&template, |captures: &Captures| {
+            let key = &captures[1];
+
+            if let Some((_, value)) = vars.iter().rev().find(|(k, _)| k == key) {
+                return value.clone();
+            }
+
+            match key {
+                "title" => title.to_string(),
+                "date" => now.format("%Y-%m-%d").to_string(),
+                "time" => now.format("%H:%M").to_string(),
+                "user" => user.clone(),
+                _ => {
+                    println!("Template variable '{key}' has no value");
+                    captures[0].to_string()
+                }
+            }
+        })
+        .into_owned()
+}
+
+pub fn note_templates() {
+    let directory = templates_dir();
+    let templates = available_templates(&directory);
+
+    if templates.is_empty() {
+        println!("No templates found in '{directory}'");
+        return;
+    }
+
+    println!("{directory}/");
+
+    for template in templates {
+        println!("\t{template}");
+    }
+}
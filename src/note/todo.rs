@@ -0,0 +1,170 @@
+use std::fs;
+
+use clap::Subcommand;
+use regex::Regex;
+
+use super::{crypt, find_note_exact, is_code_fence, notes_dir, notes_in, write_note};
+use crate::error;
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Tick off the item on a line of a note, given as NOTE:LINE
+    Check {
+        #[clap(value_parser)]
+        item: String,
+    },
+}
+
+/// A checklist item on a line of a note
+struct Item {
+    /// Line number, starting at 1
+    line: usize,
+    done: bool,
+    text: String,
+}
+
+/// Matches a checklist item, capturing its prefix, mark, separator and text
+#[must_use]
+pub fn item_regex() -> Regex {
+    match Regex::new(r"^(\s*[-*+] \[)([ xX])(\] ?)(.*)$") {
+        Ok(re) => re,
+        Err(e) => error!("Error with checklist regex: {e}"), // User should not receive this message
+    }
+}
+
+/// Find the checklist items in a note, ignoring any inside code blocks
+fn items(regex: &Regex, contents: &str) -> Vec<Item> {
+    let mut in_code_block = false;
+
+    contents
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            if is_code_fence(line) {
+                in_code_block = !in_code_block;
+            }
+
+            if in_code_block {
+                return None;
+            }
+
+            regex.captures(line).map(|captures| Item {
+                line: index + 1,
+                done: &captures[2] != " ",
+                text: captures[4].to_string(),
+            })
+        })
+        .collect()
+}
+
+/// List the open (or completed) items of every note, optionally only within a folder
+pub fn note_todo(done: bool, folder: Option<&str>) {
+    let dir_path = notes_dir();
+    let regex = item_regex();
+
+    let (mut total_open, mut total_done, mut notes) = (0, 0, 0);
+
+    for note in notes_in(&dir_path, folder) {
+        if crypt::is_encrypted(&note) {
+            continue;
+        }
+
+        let Ok(contents) = fs::read_to_string(format!("{dir_path}/{note}")) else {
+            continue;
+        };
+
+        let items = items(&regex, &contents);
+        let open = items.iter().filter(|item| !item.done).count();
+        let completed = items.len() - open;
+
+        total_open += open;
+        total_done += completed;
+
+        let shown = items
+            .iter()
+            .filter(|item| item.done == done)
+            .collect::<Vec<&Item>>();
+
+        if shown.is_empty() {
+            continue;
+        }
+
+        notes += 1;
+
+        println!("{note} ({open} open, {completed} done)");
+
+        for item in shown {
+            println!(
+                "\t{:>4}  [{}] {}",
+                item.line,
+                if item.done { 'x' } else { ' ' },
+                item.text
+            );
+        }
+    }
+
+    if notes > 0 {
+        println!();
+    }
+
+    println!("{total_open} open, {total_done} done");
+}
+
+fn todo_check(item: &str) {
+    let Some((name, line)) = item.rsplit_once(':') else {
+        error!("Expected an item in the form NOTE:LINE, got '{item}'");
+    };
+
+    let line = match line.parse::<usize>() {
+        Ok(line) if line > 0 => line,
+        _ => error!("'{line}' is not a line number"),
+    };
+
+    let dir_path = notes_dir();
+    let note = find_note_exact(&dir_path, name);
+    let path = format!("{dir_path}/{note}");
+
+    if crypt::is_encrypted(&path) {
+        error!("Cannot check items in encrypted note '{note}', open it instead");
+    }
+
+    let contents =
+        fs::read_to_string(&path).unwrap_or_else(|e| error!("Could not read note '{path}': {e}"));
+
+    let regex = item_regex();
+
+    let Some(captures) = contents
+        .lines()
+        .nth(line - 1)
+        .and_then(|text| regex.captures(text))
+    else {
+        error!("Line {line} of '{note}' is not a checklist item");
+    };
+
+    if &captures[2] != " " {
+        error!("'{}' is already done", &captures[4]);
+    }
+
+    let checked = format!("{}x{}{}", &captures[1], &captures[3], &captures[4]);
+
+    let mut updated = contents
+        .lines()
+        .enumerate()
+        .map(|(index, text)| if index == line - 1 { &checked } else { text })
+        .collect::<Vec<&str>>()
+        .join("\n");
+
+    if contents.ends_with('\n') {
+        updated.push('\n');
+    }
+
+    write_note(&path, Some(&contents), updated);
+
+    println!("Checked '{}' in '{note}'", &captures[4]);
+}
+
+pub fn parse_command(command: &Commands) {
+    match command {
+        Commands::Check { item } => todo_check(item),
+    }
+}
@@ -0,0 +1,442 @@
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use clap::ValueEnum;
+use regex::{Captures, Regex};
+
+use super::links::{
+    is_markdown, join_normalised, markdown_link_regex, parent_dir, relative_path, strip_extension,
+    wiki_link_regex,
+};
+use super::{
+    assets, config, crypt, enex, git, is_code_fence, meta, note_files, notebook, notes_dir,
+};
+use crate::{error, file, get_all_files, verify_filename};
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImportFormat {
+    /// Convert Obsidian links, embeds and tags, moving attachments into the assets folder
+    Obsidian,
+    /// Copy every file as it is
+    Markdown,
+    /// Convert the notes in an Evernote `.enex` export, saving their attachments
+    Enex,
+}
+
+fn inline_tag_regex() -> Regex {
+    match Regex::new(r"(?:^|\s)#([\w/-]*[A-Za-z_/-][\w/-]*)") {
+        Ok(re) => re,
+        Err(e) => error!("Error with tag regex: {e}"), // User should not receive this message
+    }
+}
+
+/// Find a file the way Obsidian does, by its path or by its name anywhere in the vault
+///
+/// `paths` must be sorted, so the least nested match is used when a name is ambiguous
+fn resolve_obsidian<'a>(paths: &'a [String], target: &str, strip_md: bool) -> Option<&'a str> {
+    let key = |path: &'a str| {
+        if strip_md {
+            strip_extension(path)
+        } else {
+            path
+        }
+    };
+
+    let target = target.trim().trim_start_matches('/');
+    let target = if strip_md && is_markdown(target) {
+        strip_extension(target)
+    } else {
+        target
+    };
+
+    paths
+        .iter()
+        .map(String::as_str)
+        .filter(|path| {
+            let key = key(path);
+            key == target || key.ends_with(&format!("/{target}"))
+        })
+        .min_by_key(|path| path.matches('/').count())
+}
+
+/// Tags written inline in the body of a note, outside of code blocks
+fn inline_tags(body: &str) -> Vec<String> {
+    let regex = inline_tag_regex();
+    let mut in_code_block = false;
+    let mut tags = Vec::new();
+
+    for line in body.lines() {
+        if is_code_fence(line) {
+            in_code_block = !in_code_block;
+        }
+
+        if !in_code_block {
+            tags.extend(
+                regex
+                    .captures_iter(line)
+                    .map(|captures| captures[1].to_string()),
+            );
+        }
+    }
+
+    tags
+}
+
+/// Everything needed to convert the notes of an Obsidian vault
+struct Vault<'a> {
+    source: &'a str,
+    notes: Vec<String>,
+    attachments: Vec<String>,
+    /// Where each attachment was stored, relative to the notes directory
+    stored: Vec<(String, String)>,
+}
+
+impl Vault<'_> {
+    fn stored_attachment(&self, attachment: &str) -> Option<&str> {
+        self.stored
+            .iter()
+            .find(|(original, _)| original == attachment)
+            .map(|(_, asset)| asset.as_str())
+    }
+
+    fn convert_links(&self, note: &str, body: &str) -> String {
+        let body = wiki_link_regex().replace_all(body, |captures: &Captures| {
+            let is_embed = !captures[1].is_empty();
+            let target = &captures[2];
+            let heading = captures.get(3).map_or("", |m| m.as_str());
+            let alias = captures.get(4).map(|m| m.as_str());
+
+            if let Some(linked) = resolve_obsidian(&self.notes, target, true) {
+                // Notes can't be embedded, so embeds become links
+                let alias = alias.map_or_else(String::new, |alias| format!("|{alias}"));
+                return format!("[[{}{heading}{alias}]]", strip_extension(linked));
+            }
+
+            let asset = resolve_obsidian(&self.attachments, target, false)
+                .and_then(|attachment| self.stored_attachment(attachment));
+
+            match asset {
+                Some(asset) if is_embed => assets::markdown_link(note, asset, target),
+                Some(asset) => format!(
+                    "[{}]({})",
+                    alias.unwrap_or_default(),
+                    relative_path(parent_dir(note), asset)
+                ),
+                None => captures[0].to_string(),
+            }
+        });
+
+        // Relative links to attachments need to point into the assets folder
+        markdown_link_regex()
+            .replace_all(&body, |captures: &Captures| {
+                let asset = join_normalised(parent_dir(note), &captures[3])
+                    .and_then(|attachment| self.stored_attachment(&attachment));
+
+                asset.map_or_else(
+                    || captures[0].to_string(),
+                    |asset| {
+                        format!(
+                            "{}[{}]({})",
+                            &captures[1],
+                            &captures[2],
+                            relative_path(parent_dir(note), asset)
+                        )
+                    },
+                )
+            })
+            .into_owned()
+    }
+
+    /// Convert a note's YAML metadata and inline tags into ntsr front-matter
+    ///
+    /// Also returns the keys of the metadata which couldn't be converted and were left out
+    fn convert(&self, note: &str) -> (String, Vec<String>) {
+        let path = format!("{}/{note}", self.source);
+
+        let contents =
+            fs::read_to_string(&path).unwrap_or_else(|e| error!("Could not read '{path}': {e}"));
+
+        let (block, body) = meta::split(&contents);
+        let left_out = meta::other_keys(&contents);
+        let mut front_matter = meta::parse(&contents).unwrap_or_default();
+
+        for tag in inline_tags(body) {
+            if !front_matter.tags.contains(&tag) {
+                front_matter.tags.push(tag);
+            }
+        }
+
+        let body = self.convert_links(note, body);
+
+        let wants_front_matter =
+            config::get().map_or(true, |config| config.front_matter.unwrap_or(true));
+
+        if block.is_none() && front_matter.tags.is_empty() && !wants_front_matter {
+            return (body, left_out);
+        }
+
+        if front_matter.title.is_none() {
+            front_matter.title = Path::new(note)
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .map(str::to_string);
+        }
+
+        if front_matter.created.is_none() {
+            front_matter.created = fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .map(meta::format_time);
+        }
+
+        (format!("{}{body}", front_matter.render()), left_out)
+    }
+}
+
+fn write_new(destination: &str, contents: &[u8]) {
+    file::create_parent_dirs(&destination.to_string());
+
+    if let Err(e) = fs::write(destination, contents) {
+        error!("Could not write '{destination}': {e}");
+    }
+}
+
+/// Every file in a folder being imported
+fn source_files(source: &str) -> Vec<String> {
+    if !Path::new(source).is_dir() {
+        error!("'{source}' is not a directory");
+    }
+
+    // Hidden folders such as `.obsidian` are left out
+    get_all_files(source)
+}
+
+pub fn note_import(source: &str, format: ImportFormat) {
+    let source = source.trim_end_matches('/');
+
+    let dir_path = notes_dir();
+
+    let mut conflicts = Vec::new();
+    let mut problems = Vec::new();
+    // Everything is committed at the end, so an import is a single commit
+    let mut written = Vec::new();
+    let mut imported_notes = 0;
+    let mut imported_attachments = 0;
+
+    match format {
+        ImportFormat::Enex => {
+            // Evernote exports are single files, which report what was imported themselves
+            enex::note_import(source);
+            return;
+        }
+        ImportFormat::Markdown => {
+            for file_name in &source_files(source) {
+                let destination = format!("{dir_path}/{file_name}");
+
+                if verify_filename(&destination).is_some() {
+                    conflicts.push(file_name.clone());
+                    continue;
+                }
+
+                let contents = fs::read(format!("{source}/{file_name}"))
+                    .unwrap_or_else(|e| error!("Could not read '{source}/{file_name}': {e}"));
+                write_new(&destination, &contents);
+                written.push(destination);
+
+                if is_markdown(file_name) {
+                    imported_notes += 1;
+                } else {
+                    imported_attachments += 1;
+                }
+            }
+        }
+        ImportFormat::Obsidian => {
+            let (notes, attachments): (Vec<String>, Vec<String>) = source_files(source)
+                .into_iter()
+                .partition(|file_name| is_markdown(file_name));
+
+            // Attachments are stored by their contents, so they never conflict
+            let stored = attachments
+                .iter()
+                .map(|attachment| {
+                    let asset = assets::store(&dir_path, &format!("{source}/{attachment}"));
+                    (attachment.clone(), asset)
+                })
+                .collect::<Vec<(String, String)>>();
+            imported_attachments = stored.len();
+            written.extend(
+                stored
+                    .iter()
+                    .map(|(_, asset)| format!("{dir_path}/{asset}")),
+            );
+
+            let vault = Vault {
+                source,
+                notes,
+                attachments,
+                stored,
+            };
+
+            for note in &vault.notes {
+                let destination = format!("{dir_path}/{note}");
+
+                if verify_filename(&destination).is_some() {
+                    conflicts.push(note.clone());
+                    continue;
+                }
+
+                let (contents, left_out) = vault.convert(note);
+                write_new(&destination, contents.as_bytes());
+                written.push(destination);
+
+                if !left_out.is_empty() {
+                    problems.push((note.clone(), left_out));
+                }
+
+                imported_notes += 1;
+            }
+        }
+    }
+
+    if git::enabled() {
+        git::commit_files(
+            &notebook::dir_containing(&dir_path),
+            &written,
+            &format!("import: {source}"),
+        );
+    }
+
+    println!(
+        "Imported {imported_notes} notes and {imported_attachments} attachments from '{source}'"
+    );
+
+    if !conflicts.is_empty() {
+        println!(
+            "Skipped {} files which already exist in '{dir_path}':",
+            conflicts.len()
+        );
+
+        for conflict in conflicts {
+            println!("\t{conflict}");
+        }
+    }
+
+    if !problems.is_empty() {
+        println!("Some content could not be converted:");
+
+        for (note, keys) in problems {
+            println!("\t{note}: front-matter keys {}", keys.join(", "));
+        }
+    }
+}
+
+/// Quote a string for YAML front-matter
+fn yaml_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Convert a note into Obsidian's format, with YAML front-matter and links to markdown files
+///
+/// Only the metadata ntsr reads is kept, see `meta::other_keys` for what's left out
+#[must_use]
+pub fn to_obsidian(contents: &str) -> String {
+    let (block, body) = meta::split(contents);
+
+    let body = wiki_link_regex().replace_all(body, |captures: &Captures| {
+        let target = captures[2].trim();
+
+        target.strip_suffix(".txt").map_or_else(
+            || captures[0].to_string(),
+            |stem| captures[0].replacen(target, stem, 1),
+        )
+    });
+
+    let Some(front_matter) = block.and_then(|_| meta::parse(contents)) else {
+        return body.into_owned();
+    };
+
+    let mut yaml = String::from("---\n");
+
+    for (key, value) in [
+        ("title", &front_matter.title),
+        ("created", &front_matter.created),
+        ("updated", &front_matter.updated),
+    ] {
+        if let Some(value) = value {
+            let _ = writeln!(yaml, "{key}: {}", yaml_string(value));
+        }
+    }
+
+    if !front_matter.tags.is_empty() {
+        yaml.push_str("tags:\n");
+
+        for tag in &front_matter.tags {
+            let _ = writeln!(yaml, "  - {}", yaml_string(tag));
+        }
+    }
+
+    yaml.push_str("---\n");
+
+    format!("{yaml}{body}")
+}
+
+/// Name of a note in an Obsidian vault, which only shows markdown files
+fn obsidian_name(file_name: &str) -> String {
+    file_name
+        .strip_suffix(".txt")
+        .map_or_else(|| file_name.to_string(), |stem| format!("{stem}.md"))
+}
+
+/// Write every note and attachment to a folder which can be opened as an Obsidian vault
+pub fn export_vault(dir_path: &str, output_dir: &str) {
+    let mut exported = 0;
+    let mut skipped = Vec::new();
+    let mut left_out = Vec::new();
+
+    for note in note_files(dir_path) {
+        if crypt::is_encrypted(&note) {
+            skipped.push(note);
+            continue;
+        }
+
+        let Ok(contents) = fs::read_to_string(format!("{dir_path}/{note}")) else {
+            skipped.push(note);
+            continue;
+        };
+
+        let destination = format!("{output_dir}/{}", obsidian_name(&note));
+        file::create_parent_dirs(&destination);
+
+        if let Err(e) = fs::write(&destination, to_obsidian(&contents)) {
+            error!("Could not write '{destination}': {e}");
+        }
+
+        let keys = meta::other_keys(&contents);
+        if !keys.is_empty() {
+            left_out.push((note, keys));
+        }
+
+        exported += 1;
+    }
+
+    let attachments = assets::copy_to(dir_path, output_dir);
+
+    println!("Exported {exported} notes and {attachments} attachments to '{output_dir}'");
+
+    if !skipped.is_empty() {
+        println!("Skipped {} encrypted or unreadable notes:", skipped.len());
+
+        for note in skipped {
+            println!("\t{note}");
+        }
+    }
+
+    if !left_out.is_empty() {
+        println!("Some front-matter could not be exported:");
+
+        for (note, keys) in left_out {
+            println!("\t{note}: {}", keys.join(", "));
+        }
+    }
+}
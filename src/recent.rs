@@ -0,0 +1,240 @@
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use chrono::Local;
+use clap::ValueEnum;
+
+use crate::commands::run_editor;
+use crate::temp_control::cache_dir;
+use crate::{config, error, note, verify_filename};
+
+const RECENT_FILE: &str = "recent.log";
+const LOCK_FILE: &str = "recent.lock";
+
+/// Entries kept once the history is compacted
+const MAX_ENTRIES: usize = 500;
+/// Extra entries allowed to build up before compacting, so it isn't rewritten on every open
+const COMPACT_SLACK: usize = 100;
+
+/// How long to wait for another invocation to release the lock
+const LOCK_ATTEMPTS: u32 = 50;
+const LOCK_RETRY_DELAY: Duration = Duration::from_millis(20);
+/// Locks older than this were left behind by an invocation which didn't finish
+const STALE_LOCK_AGE: Duration = Duration::from_secs(10);
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Kind {
+    Note,
+    Latex,
+    Rust,
+    Other,
+}
+
+impl Kind {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Note => "note",
+            Self::Latex => "latex",
+            Self::Rust => "rust",
+            Self::Other => "other",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::value_variants()
+            .iter()
+            .copied()
+            .find(|kind| kind.name() == name)
+    }
+
+    /// Work out what kind of file a path is from the directory it's in
+    fn of_path(path: &str) -> Self {
+        let Ok(config) = config::get() else {
+            return Self::Other;
+        };
+
+        let is_within = |dir: &str| !dir.is_empty() && Path::new(path).starts_with(dir);
+
+        if config.notebooks().into_values().any(is_within) {
+            Self::Note
+        } else if is_within(&config.doc) {
+            Self::Latex
+        } else if is_within(&config.dev) {
+            Self::Rust
+        } else {
+            Self::Other
+        }
+    }
+}
+
+/// A file which was opened in the editor
+struct Entry {
+    time: String,
+    kind: Kind,
+    path: String,
+}
+
+impl Entry {
+    /// Entries are stored one per line as tab separated fields
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(3, '\t');
+
+        Some(Self {
+            time: fields.next()?.to_string(),
+            kind: Kind::from_name(fields.next()?)?,
+            path: fields.next()?.to_string(),
+        })
+    }
+
+    fn line(&self) -> String {
+        format!("{}\t{}\t{}\n", self.time, self.kind.name(), self.path)
+    }
+}
+
+fn recent_path() -> String {
+    format!("{}/{RECENT_FILE}", cache_dir())
+}
+
+/// Held while the history is being written, so concurrent invocations don't lose entries
+struct Lock {
+    path: String,
+}
+
+impl Lock {
+    fn acquire() -> Option<Self> {
+        let path = format!("{}/{LOCK_FILE}", cache_dir());
+
+        for _ in 0..LOCK_ATTEMPTS {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Some(Self { path }),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    let is_stale = fs::metadata(&path)
+                        .and_then(|metadata| metadata.modified())
+                        .ok()
+                        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                        .is_some_and(|age| age > STALE_LOCK_AGE);
+
+                    if is_stale {
+                        let _ = fs::remove_file(&path);
+                    } else {
+                        thread::sleep(LOCK_RETRY_DELAY);
+                    }
+                }
+                Err(_) => return None,
+            }
+        }
+
+        None
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn read_entries() -> Vec<Entry> {
+    fs::read_to_string(recent_path())
+        .map(|contents| contents.lines().filter_map(Entry::parse).collect())
+        .unwrap_or_default()
+}
+
+/// Rewrite the history with only its newest entries
+fn compact(entries: &[Entry]) {
+    let recent_path = recent_path();
+    let temp_path = format!("{recent_path}.{}", std::process::id());
+
+    let contents = entries[entries.len().saturating_sub(MAX_ENTRIES)..]
+        .iter()
+        .map(Entry::line)
+        .collect::<String>();
+
+    if fs::write(&temp_path, contents).is_err() || fs::rename(&temp_path, &recent_path).is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+}
+
+/// Add a file to the history of recently opened files
+///
+/// Failing to record a file never stops it from being opened
+pub fn record(path: &str) {
+    if path.contains(['\t', '\n']) {
+        return;
+    }
+
+    let path = fs::canonicalize(path).map_or_else(
+        |_| path.to_string(),
+        |full_path| full_path.to_string_lossy().into_owned(),
+    );
+
+    let entry = Entry {
+        time: Local::now().format(TIME_FORMAT).to_string(),
+        kind: Kind::of_path(&path),
+        path,
+    };
+
+    let Some(_lock) = Lock::acquire() else {
+        return;
+    };
+
+    let appended = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(recent_path())
+        .and_then(|mut file| file.write_all(entry.line().as_bytes()));
+
+    if appended.is_ok() {
+        let entries = read_entries();
+
+        if entries.len() > MAX_ENTRIES + COMPACT_SLACK {
+            compact(&entries);
+        }
+    }
+}
+
+/// Get the most recently opened files, newest first, each listed once
+fn latest(kind: Option<Kind>) -> Vec<Entry> {
+    let mut seen = HashSet::new();
+
+    read_entries()
+        .into_iter()
+        .rev()
+        .filter(|entry| kind.is_none_or(|kind| entry.kind == kind))
+        .filter(|entry| seen.insert(entry.path.clone()))
+        .collect()
+}
+
+pub fn recent(count: usize, kind: Option<Kind>) {
+    let entries = latest(kind);
+
+    if entries.is_empty() {
+        println!("No files have been opened yet");
+        return;
+    }
+
+    for entry in entries.iter().take(count) {
+        println!("{}  {:<5}  {}", entry.time, entry.kind.name(), entry.path);
+    }
+}
+
+/// Reopen the most recently opened file which still exists
+pub fn last(kind: Option<Kind>) {
+    let Some(entry) = latest(kind)
+        .into_iter()
+        .find(|entry| verify_filename(&entry.path).is_some())
+    else {
+        error!("No recently opened files found");
+    };
+
+    match entry.kind {
+        Kind::Note => note::edit_note(&entry.path),
+        _ => run_editor(&entry.path),
+    }
+}
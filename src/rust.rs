@@ -1,11 +1,11 @@
-use std::fs::remove_dir_all;
 use std::path::Path;
 
 use clap::Subcommand;
 
 use crate::commands::{run_command, run_editor};
 use crate::config;
-use crate::{confirm, error, get_files, get_matching_files, verify_filename};
+use crate::trash;
+use crate::{confirm, error, fuzzy, get_files, get_matching_files, verify_filename};
 
 #[derive(Subcommand)]
 pub enum Commands {
@@ -65,21 +65,42 @@ fn rust_new(project_name: &String) {
 fn rust_open(project_name: &String, file_name: &Option<String>) {
     match config::get() {
         Ok(config) => {
+            let rust_dir = format!("{}/Rust", config.dev);
+
+            let project_name = if verify_filename(&format!("{rust_dir}/{project_name}")).is_some() {
+                project_name.clone()
+            } else {
+                let candidates = get_matching_files(&rust_dir, r"^[a-zA-Z]");
+
+                fuzzy::choose(project_name, &candidates, "project")
+            };
+
             let path_name = match file_name {
                 Some(path) => {
-                    format!(
-                        "{project_name}/src/{}{}",
-                        path,
+                    let src_name = format!(
+                        "{path}{}",
                         match Path::new(path).extension() {
                             Some(_) => "",
                             None => ".rs",
                         }
-                    )
+                    );
+                    let src_dir = format!("{rust_dir}/{project_name}/src");
+
+                    if verify_filename(&format!("{src_dir}/{src_name}")).is_some() {
+                        format!("{project_name}/src/{src_name}")
+                    } else {
+                        let candidates = get_matching_files(&src_dir, r"^[^.]");
+
+                        format!(
+                            "{project_name}/src/{}",
+                            fuzzy::choose(path, &candidates, "file")
+                        )
+                    }
                 }
-                None => project_name.clone(),
+                None => project_name,
             };
 
-            let full_filename = format!("{}/Rust/{}", config.dev, path_name);
+            let full_filename = format!("{rust_dir}/{path_name}");
 
             match verify_filename(&full_filename) {
                 Some(name) => run_editor(name),
@@ -177,10 +198,11 @@ fn rust_remove(project_name: &String) {
             match verify_filename(&filename) {
                 Some(name) => {
                     if confirm!("remove {project_name}") {
-                        match remove_dir_all(name) {
-                            Ok(_) => println!("Successfully deleted {name}"),
-                            Err(e) => error!("Could not remove project '{name}': {e}"),
-                        }
+                        let id = trash::move_to_trash(name);
+
+                        println!(
+                            "Moved {name} to the trash (restore with 'ntsr trash restore {id}')"
+                        );
                     } else {
                         // User denies confirmation
                         std::process::exit(0x1001);
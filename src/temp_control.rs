@@ -6,6 +6,18 @@ use std::fs;
 const DEFAULT_FILE_DIR: &str = ".cache/ntsr";
 const DEFAULT_FILE_NAME: &str = "temp.toml";
 
+/// Get the cache directory, creating it if it doesn't exist
+#[must_use]
+pub fn cache_dir() -> String {
+    let directory = format!("{}/{DEFAULT_FILE_DIR}", get_home());
+
+    if let Err(e) = fs::create_dir_all(&directory) {
+        error!("Could not create cache directory '{directory}': {e}");
+    }
+
+    directory
+}
+
 #[must_use]
 pub fn get_file() -> fs::File {
     let directory = format!("{}/{DEFAULT_FILE_DIR}", get_home());
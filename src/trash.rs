@@ -0,0 +1,289 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, Duration, Local};
+use clap::Subcommand;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::temp_control::cache_dir;
+use crate::{confirm, error, file, verify_filename};
+
+const TRASH_DIR: &str = "trash";
+const INDEX_FILE: &str = "index.toml";
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// List the files in the trash
+    List,
+    /// Restore a file from the trash to where it was deleted from
+    Restore {
+        /// ID of the trashed file
+        #[clap(value_parser)]
+        id: u64,
+
+        /// Overwrite anything which now exists at the original location
+        #[clap(short, long)]
+        force: bool,
+    },
+    /// Permanently delete files in the trash
+    Empty {
+        /// Only delete files trashed longer ago than this (e.g. 30d, 12h, 2w)
+        #[clap(long, value_parser = parse_age)]
+        older_than: Option<Duration>,
+    },
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Index {
+    /// ID for the next trashed file, which only ever goes up so IDs are never reused
+    #[serde(default)]
+    next_id: u64,
+    #[serde(default)]
+    entries: Vec<Entry>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Entry {
+    id: u64,
+    /// Full path the file was deleted from
+    original: String,
+    /// When the file was deleted, in RFC 3339 format
+    deleted: String,
+}
+
+impl Entry {
+    fn deleted_at(&self) -> Option<DateTime<Local>> {
+        DateTime::parse_from_rfc3339(&self.deleted)
+            .ok()
+            .map(|date| date.with_timezone(&Local))
+    }
+}
+
+/// Parse an age such as `30d` into a duration
+///
+/// # Errors
+/// Returns an error if the age is not a number followed by one of `m`, `h`, `d` or `w`, or is
+/// too long to be represented
+pub fn parse_age(arg: &str) -> Result<Duration, String> {
+    let invalid = || format!("Invalid age '{arg}', expected e.g. 30d, 12h, 2w or 45m");
+
+    let unit_start = arg
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (amount, unit) = arg.split_at(unit_start);
+    let amount = amount.parse::<i64>().map_err(|_| invalid())?;
+
+    let age = match unit {
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        _ => None,
+    };
+
+    age.ok_or_else(invalid)
+}
+
+fn trash_dir() -> String {
+    format!("{}/{TRASH_DIR}", cache_dir())
+}
+
+fn read_index() -> Index {
+    let index_path = format!("{}/{INDEX_FILE}", trash_dir());
+
+    fs::read_to_string(&index_path).map_or_else(
+        |_| Index::default(),
+        |contents| {
+            toml::from_str(&contents)
+                .unwrap_or_else(|e| error!("Could not read trash index '{index_path}': {e}"))
+        },
+    )
+}
+
+fn write_index(index: &Index) {
+    let index_path = format!("{}/{INDEX_FILE}", trash_dir());
+
+    let contents =
+        toml::to_string(index).unwrap_or_else(|e| error!("Could not serialise trash index: {e}"));
+
+    file::create_parent_dirs(&index_path);
+
+    if let Err(e) = fs::write(&index_path, contents) {
+        error!("Could not write trash index '{index_path}': {e}");
+    }
+}
+
+fn copy_recursive(from: &Path, to: &Path) -> std::io::Result<()> {
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+
+        Ok(())
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// Move a file or directory, falling back to copying when renaming isn't possible
+fn move_path(from: &Path, to: &Path) -> std::io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    copy_recursive(from, to)?;
+    remove_path(from)
+}
+
+fn stored_path(entry: &Entry) -> String {
+    let file_name = Path::new(&entry.original)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("file");
+
+    format!("{}/{}/{file_name}", trash_dir(), entry.id)
+}
+
+/// Move a file or directory into the trash, returning its trash ID
+#[must_use]
+pub fn move_to_trash(path: &str) -> u64 {
+    let original = Path::new(path)
+        .canonicalize()
+        .unwrap_or_else(|e| error!("Could not find '{path}': {e}"));
+
+    let mut index = read_index();
+
+    // Indexes written before the counter was kept carry on after their highest ID
+    let id = index.next_id.max(
+        index
+            .entries
+            .iter()
+            .map(|entry| entry.id + 1)
+            .max()
+            .unwrap_or(1),
+    );
+    index.next_id = id + 1;
+
+    let entry = Entry {
+        id,
+        original: original.to_string_lossy().to_string(),
+        deleted: Local::now().to_rfc3339(),
+    };
+
+    let stored = stored_path(&entry);
+    file::create_parent_dirs(&stored);
+
+    if let Err(e) = move_path(&original, Path::new(&stored)) {
+        error!("Could not move '{path}' to the trash: {e}");
+    }
+
+    index.entries.push(entry);
+    write_index(&index);
+
+    id
+}
+
+fn trash_list() {
+    let index = read_index();
+
+    if index.entries.is_empty() {
+        println!("The trash is empty");
+        return;
+    }
+
+    for entry in &index.entries {
+        let deleted = entry.deleted_at().map_or_else(
+            || entry.deleted.clone(),
+            |date| date.format("%Y-%m-%d %H:%M").to_string(),
+        );
+
+        println!("{:>4}  {deleted}  {}", entry.id, entry.original);
+    }
+}
+
+fn trash_restore(id: u64, force: bool) {
+    let mut index = read_index();
+
+    let Some(position) = index.entries.iter().position(|entry| entry.id == id) else {
+        error!("No file with ID {id} in the trash");
+    };
+    let entry = index.entries[position].clone();
+
+    if verify_filename(&entry.original).is_some() {
+        if !force {
+            error!(
+                "'{}' already exists, use --force to overwrite it",
+                entry.original
+            );
+        }
+
+        if let Err(e) = remove_path(Path::new(&entry.original)) {
+            error!("Could not remove '{}': {e}", entry.original);
+        }
+    }
+
+    file::create_parent_dirs(&entry.original);
+
+    if let Err(e) = move_path(Path::new(&stored_path(&entry)), Path::new(&entry.original)) {
+        error!("Could not restore '{}': {e}", entry.original);
+    }
+
+    // Remove the now empty folder the file was stored in
+    let _ = fs::remove_dir(format!("{}/{}", trash_dir(), entry.id));
+
+    index.entries.remove(position);
+    write_index(&index);
+
+    println!("Restored '{}'", entry.original);
+}
+
+fn trash_empty(older_than: Option<Duration>) {
+    let mut index = read_index();
+
+    let now = Local::now();
+    let (expired, kept): (Vec<Entry>, Vec<Entry>) = index.entries.into_iter().partition(|entry| {
+        older_than.is_none_or(|age| entry.deleted_at().is_some_and(|date| now - date > age))
+    });
+    index.entries = kept;
+
+    if expired.is_empty() {
+        println!("Nothing to remove from the trash");
+        return;
+    }
+
+    if !confirm!("permanently delete {} files from the trash", expired.len()) {
+        // User denies confirmation
+        std::process::exit(0x1001);
+    }
+
+    for entry in &expired {
+        let entry_dir = format!("{}/{}", trash_dir(), entry.id);
+
+        if let Err(e) = fs::remove_dir_all(&entry_dir) {
+            println!("Could not remove '{entry_dir}': {e}");
+        }
+    }
+
+    write_index(&index);
+
+    println!("Permanently deleted {} files", expired.len());
+}
+
+pub fn parse_command(command: &Commands) {
+    match command {
+        Commands::List => trash_list(),
+        Commands::Restore { id, force } => trash_restore(*id, *force),
+        Commands::Empty { older_than } => trash_empty(*older_than),
+    }
+}